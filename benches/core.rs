@@ -0,0 +1,111 @@
+//! Benchmarks for the interpreter's hot paths: decoding, executing each class of
+//! instruction, drawing, and whole frames. Run with `cargo bench`.
+
+use chip8::{op_code, OpCode, Quirks, Rom, System};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+// a system with the font loaded and registers set up for `op`s to do real work
+fn system() -> System {
+    let mut system = System::init(&Rom::new(vec![0x12, 0x00])).unwrap();
+    for vx in 0..16 {
+        system.set_v(vx, 0x11 * vx as u8 + 3);
+    }
+    system.set_i(0x300);
+    system.seed(1);
+    system
+}
+
+fn decode(c: &mut Criterion) {
+    c.bench_function("decode/all", |b| {
+        b.iter(|| {
+            for op in 0..=u16::MAX {
+                black_box(op_code::decode(black_box(op)));
+            }
+        })
+    });
+}
+
+fn execute(c: &mut Criterion) {
+    let classes = [
+        ("load", OpCode::LdVx { vx: 1, value: 0x42 }),
+        ("arithmetic", OpCode::AddVxVy { vx: 1, vy: 2 }),
+        ("logic", OpCode::XorVxVy { vx: 3, vy: 4 }),
+        ("shift", OpCode::Shl { vx: 5, vy: 6 }),
+        ("skip", OpCode::SeVxVy { vx: 7, vy: 8 }),
+        ("jump", OpCode::Jmp(0x200)),
+        ("random", OpCode::Rnd { vx: 9, value: 0x0F }),
+        ("timers", OpCode::LdDtVx(10)),
+        ("index", OpCode::AddIVx(0)),
+        ("bcd", OpCode::LdBVx(11)),
+        ("store", OpCode::LdIVx(15)),
+        ("load_registers", OpCode::LdVxI(15)),
+        ("clear", OpCode::Cls),
+    ];
+    let mut group = c.benchmark_group("execute");
+    for (name, op) in classes {
+        let mut system = system();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                system.set_i(0x300);
+                system.execute(black_box(&op)).unwrap();
+            })
+        });
+    }
+    // calls and returns have to pair up to keep the stack from growing
+    let mut system = system();
+    group.bench_function("call_return", |b| {
+        b.iter(|| {
+            system.execute(black_box(&OpCode::Call(0x300))).unwrap();
+            system.execute(black_box(&OpCode::Ret)).unwrap();
+        })
+    });
+    group.finish();
+}
+
+// DXYN goes through update_frame_buffer, so this is the cost of drawing sprites
+fn draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+    // the font's 0, then 15 rows of whatever follows it
+    for (name, x, y, n) in [("digit", 8, 8, 5), ("tall", 8, 8, 15), ("wrap", 60, 30, 15)] {
+        let mut system = system();
+        system.set_v(0, x);
+        system.set_v(1, y);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                system.set_i(0x050);
+                system
+                    .execute(black_box(&OpCode::Drw { vx: 0, vy: 1, n }))
+                    .unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn frames(c: &mut Criterion) {
+    let rom = Rom::new(include_bytes!("../roms/stars.ch8").to_vec());
+    let mut group = c.benchmark_group("frame");
+    for (name, blocks) in [("interpreter", false), ("blocks", true)] {
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                || {
+                    let mut system = System::init(&rom).unwrap();
+                    system.set_quirks(Quirks::default());
+                    system.set_blocks(blocks);
+                    system.seed(1);
+                    system
+                },
+                |system| {
+                    for _ in 0..60 {
+                        system.run_frame(1000).unwrap();
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode, execute, draw, frames);
+criterion_main!(benches);
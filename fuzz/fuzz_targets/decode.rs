@@ -0,0 +1,22 @@
+//! Decodes and disassembles arbitrary bytes. Nothing may panic, and an instruction
+//! that decodes must encode to something that decodes the same.
+//!
+//!     cargo +nightly fuzz run decode fuzz/corpus/decode fuzz/seeds/decode
+
+#![no_main]
+
+use chip8::{asm, op_code, OpCode, ROM_START};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for op in data.chunks_exact(2) {
+        let op = u16::from_be_bytes([op[0], op[1]]);
+        for decode in [op_code::decode, op_code::decode_chip8x] {
+            let op_code = decode(op);
+            if op_code != OpCode::Unknown {
+                assert_eq!(op_code, decode(op_code::encode(&op_code)), "{:#06X}", op);
+            }
+        }
+    }
+    asm::disassemble(data, ROM_START);
+});
@@ -0,0 +1,48 @@
+//! Runs arbitrary ROMs with arbitrary keys for a bounded number of frames. Faults are
+//! fine, as broken programs fault on real hardware too, but nothing may panic.
+//!
+//! The input is a settings byte, a frame count, a key byte for each frame and then
+//! the ROM. The settings byte's low 3 bits pick the quirk profile, bit 3 VIP timing,
+//! bit 4 the block engine and bit 5 hi-res mode. A key byte of 0x10 or more is no key.
+//!
+//!     cargo +nightly fuzz run run fuzz/corpus/run fuzz/seeds/run
+
+#![no_main]
+
+use chip8::timing::Timing;
+use chip8::{Quirks, Rom, System};
+use libfuzzer_sys::fuzz_target;
+
+const MAX_FRAMES: usize = 60;
+
+fuzz_target!(|data: &[u8]| {
+    let [settings, frames, rest @ ..] = data else {
+        return;
+    };
+    let frames = (*frames as usize % (MAX_FRAMES + 1)).min(rest.len());
+    let (keys, rom) = rest.split_at(frames);
+
+    let mut rom = Rom::new(rom.to_vec());
+    if settings & 0x20 != 0 {
+        rom = rom.hires();
+    }
+    // too big for memory
+    let Ok(mut system) = System::init(&rom) else {
+        return;
+    };
+    let profile = Quirks::PROFILES[(settings & 0x07) as usize % Quirks::PROFILES.len()];
+    system.set_quirks(Quirks::profile(profile).unwrap());
+    system.set_blocks(settings & 0x10 != 0);
+    system.seed(0);
+    let timing = match settings & 0x08 != 0 {
+        true => Timing::Vip,
+        false => Timing::Ipf(100),
+    };
+
+    for key in keys {
+        system.set_key(Some(*key).filter(|key| *key < 0x10));
+        if timing.run_frame(&mut system).is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,367 @@
+use crate::op_code::{self, OpCode};
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+
+// Both directions use the mnemonics from Cowgod's CHIP-8 technical reference,
+// e.g. `LD V1, 0x0A`, `DRW V0, V1, 5` or `LD [I], V3`.
+
+/// The assembly mnemonic for a single instruction.
+pub fn mnemonic(op: &OpCode) -> String {
+    match *op {
+        OpCode::Cls => "CLS".to_string(),
+        OpCode::Ret => "RET".to_string(),
+        OpCode::Jmp(addr) => format!("JP {:#05X}", addr),
+        OpCode::Call(addr) => format!("CALL {:#05X}", addr),
+        OpCode::Se { vx, value } => format!("SE V{:X}, {:#04X}", vx, value),
+        OpCode::Sne { vx, value } => format!("SNE V{:X}, {:#04X}", vx, value),
+        OpCode::SeVxVy { vx, vy } => format!("SE V{:X}, V{:X}", vx, vy),
+        OpCode::LdVx { vx, value } => format!("LD V{:X}, {:#04X}", vx, value),
+        OpCode::AddVx { vx, value } => format!("ADD V{:X}, {:#04X}", vx, value),
+        OpCode::LdVxVy { vx, vy } => format!("LD V{:X}, V{:X}", vx, vy),
+        OpCode::OrVxVy { vx, vy } => format!("OR V{:X}, V{:X}", vx, vy),
+        OpCode::AndVxVy { vx, vy } => format!("AND V{:X}, V{:X}", vx, vy),
+        OpCode::XorVxVy { vx, vy } => format!("XOR V{:X}, V{:X}", vx, vy),
+        OpCode::AddVxVy { vx, vy } => format!("ADD V{:X}, V{:X}", vx, vy),
+        OpCode::Sub { vx, vy } => format!("SUB V{:X}, V{:X}", vx, vy),
+        OpCode::Shr { vx, vy } => format!("SHR V{:X}, V{:X}", vx, vy),
+        OpCode::SubN { vx, vy } => format!("SUBN V{:X}, V{:X}", vx, vy),
+        OpCode::Shl { vx, vy } => format!("SHL V{:X}, V{:X}", vx, vy),
+        OpCode::SneVxVy { vx, vy } => format!("SNE V{:X}, V{:X}", vx, vy),
+        OpCode::LdI(addr) => format!("LD I, {:#05X}", addr),
+        OpCode::JmpV0(addr) => format!("JP V0, {:#05X}", addr),
+        OpCode::Rnd { vx, value } => format!("RND V{:X}, {:#04X}", vx, value),
+        OpCode::Drw { vx, vy, n } => format!("DRW V{:X}, V{:X}, {}", vx, vy, n),
+        OpCode::Skp(vx) => format!("SKP V{:X}", vx),
+        OpCode::Sknp(vx) => format!("SKNP V{:X}", vx),
+        OpCode::LdVxDt(vx) => format!("LD V{:X}, DT", vx),
+        OpCode::LdVxK(vx) => format!("LD V{:X}, K", vx),
+        OpCode::LdDtVx(vx) => format!("LD DT, V{:X}", vx),
+        OpCode::LdStVx(vx) => format!("LD ST, V{:X}", vx),
+        OpCode::AddIVx(vx) => format!("ADD I, V{:X}", vx),
+        OpCode::LdFVx(vx) => format!("LD F, V{:X}", vx),
+        OpCode::LdBVx(vx) => format!("LD B, V{:X}", vx),
+        OpCode::LdIVx(vx) => format!("LD [I], V{:X}", vx),
+        OpCode::LdVxI(vx) => format!("LD V{:X}, [I]", vx),
+        OpCode::StepBg => "BG".to_string(),
+        OpCode::AddNibbles { vx, vy } => format!("ADDN V{:X}, V{:X}", vx, vy),
+        OpCode::Colour { vx, vy, n } => format!("COL V{:X}, V{:X}, {}", vx, vy, n),
+        OpCode::SkpKey2(vx) => format!("SKP2 V{:X}", vx),
+        OpCode::SknpKey2(vx) => format!("SKNP2 V{:X}", vx),
+        OpCode::OutVx(vx) => format!("OUT V{:X}", vx),
+        OpCode::InVx(vx) => format!("IN V{:X}", vx),
+        OpCode::Unknown => "???".to_string(),
+    }
+}
+
+/// One line per instruction: address, raw opcode and mnemonic. Anything that doesn't
+/// decode is written as data so the listing can be fed back into `assemble`.
+pub fn disassemble(rom: &[u8], origin: u16) -> String {
+    let mut listing = String::new();
+    for (i, bytes) in rom.chunks(2).enumerate() {
+        let addr = origin as usize + i * 2;
+        let line = match *bytes {
+            [hi, lo] => {
+                let op = (hi as u16) << 8 | lo as u16;
+                match op_code::decode(op) {
+                    OpCode::Unknown => format!("{:#05X}  {:04X}  DW {:#06X}", addr, op, op),
+                    op_code => format!("{:#05X}  {:04X}  {}", addr, op, mnemonic(&op_code)),
+                }
+            }
+            [b] => format!("{:#05X}  {:02X}    DB {:#04X}", addr, b, b),
+            _ => unreachable!(),
+        };
+        listing.push_str(&line);
+        listing.push('\n');
+    }
+    listing
+}
+
+#[derive(Debug, PartialEq)]
+enum Operand {
+    V(usize),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    // the font digit and BCD targets of LD F, Vx and LD B, Vx
+    F,
+    B,
+    // a number or a label, resolved once every label is known
+    Value(String),
+}
+
+struct Line<'a> {
+    number: usize,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+}
+
+/// Assembles source written in the syntax `disassemble` produces. Labels end in `:`,
+/// comments start with `;`, and `DB`/`DW` emit raw bytes and words. An optional
+/// leading address column (as in a listing) is ignored.
+pub fn assemble(source: &str, origin: u16) -> Result<Vec<u8>> {
+    let mut labels = HashMap::new();
+    let mut lines = Vec::new();
+    let mut addr = origin;
+
+    // first pass: find every label's address
+    for (i, text) in source.lines().enumerate() {
+        let mut text = text.split(';').next().unwrap().trim();
+        if let Some((label, rest)) = text.split_once(':') {
+            if !is_label(label.trim()) {
+                bail!("line {}: invalid label {:?}", i + 1, label);
+            }
+            labels.insert(label.trim().to_lowercase(), addr);
+            text = rest.trim();
+        }
+        let text = strip_listing_columns(text);
+        if text.is_empty() {
+            continue;
+        }
+        let (mnemonic, operands) = match text.split_once(char::is_whitespace) {
+            Some((mnemonic, operands)) => (mnemonic, operands.split(',').map(str::trim).collect()),
+            None => (text, Vec::new()),
+        };
+        let mnemonic = mnemonic.to_uppercase();
+        let size = match mnemonic.as_str() {
+            "DB" => operands.len(),
+            "DW" => operands.len() * 2,
+            _ => 2,
+        };
+        lines.push(Line {
+            number: i + 1,
+            mnemonic,
+            operands,
+        });
+        addr = addr
+            .checked_add(size as u16)
+            .filter(|addr| *addr <= 0x1000)
+            .ok_or_else(|| anyhow!("line {}: program does not fit in memory", i + 1))?;
+    }
+
+    // second pass: emit the bytes
+    let mut rom = Vec::new();
+    for line in lines {
+        let bytes = emit(&line, &labels)
+            .with_context(|| format!("line {}: {}", line.number, line.mnemonic))?;
+        rom.extend(bytes);
+    }
+    Ok(rom)
+}
+
+fn is_label(label: &str) -> bool {
+    let mut chars = label.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// drops the "0x200  00E0  " prefix that `disassemble` puts in front of each mnemonic
+fn strip_listing_columns(text: &str) -> &str {
+    let is_hex = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit());
+    let mut columns = text.split_whitespace();
+    match (columns.next(), columns.next()) {
+        (Some(addr), Some(raw)) if addr.starts_with("0x") && is_hex(&addr[2..]) && is_hex(raw) => {
+            text[addr.len()..].trim_start()[raw.len()..].trim_start()
+        }
+        _ => text,
+    }
+}
+
+fn emit(line: &Line, labels: &HashMap<String, u16>) -> Result<Vec<u8>> {
+    let value = |text: &str, max: u16| -> Result<u16> {
+        let value = match labels.get(&text.to_lowercase()) {
+            Some(addr) => *addr,
+            None => parse_number(text)?,
+        };
+        if value > max {
+            bail!("{} is larger than {:#X}", text, max);
+        }
+        Ok(value)
+    };
+
+    match line.mnemonic.as_str() {
+        "DB" => {
+            return line
+                .operands
+                .iter()
+                .map(|b| value(b, 0xFF).map(|b| b as u8))
+                .collect()
+        }
+        "DW" => {
+            let mut bytes = Vec::new();
+            for w in &line.operands {
+                bytes.extend(value(w, 0xFFFF)?.to_be_bytes());
+            }
+            return Ok(bytes);
+        }
+        _ => {}
+    }
+
+    let operands: Vec<Operand> = line.operands.iter().map(|o| operand(o)).collect();
+    let addr = |text: &str| value(text, 0x0FFF);
+    let byte = |text: &str| value(text, 0x00FF).map(|b| b as u8);
+
+    use Operand::*;
+    let op = match (line.mnemonic.as_str(), operands.as_slice()) {
+        ("CLS", []) => OpCode::Cls,
+        ("RET", []) => OpCode::Ret,
+        ("JP", [Value(a)]) => OpCode::Jmp(addr(a)?),
+        ("JP", [V(0), Value(a)]) => OpCode::JmpV0(addr(a)?),
+        ("CALL", [Value(a)]) => OpCode::Call(addr(a)?),
+        ("SE", [V(vx), Value(b)]) => OpCode::Se {
+            vx: *vx,
+            value: byte(b)?,
+        },
+        ("SE", [V(vx), V(vy)]) => OpCode::SeVxVy { vx: *vx, vy: *vy },
+        ("SNE", [V(vx), Value(b)]) => OpCode::Sne {
+            vx: *vx,
+            value: byte(b)?,
+        },
+        ("SNE", [V(vx), V(vy)]) => OpCode::SneVxVy { vx: *vx, vy: *vy },
+        ("LD", [V(vx), Value(b)]) => OpCode::LdVx {
+            vx: *vx,
+            value: byte(b)?,
+        },
+        ("LD", [V(vx), V(vy)]) => OpCode::LdVxVy { vx: *vx, vy: *vy },
+        ("LD", [I, Value(a)]) => OpCode::LdI(addr(a)?),
+        ("LD", [V(vx), K]) => OpCode::LdVxK(*vx),
+        ("LD", [V(vx), Dt]) => OpCode::LdVxDt(*vx),
+        ("LD", [F, V(vx)]) => OpCode::LdFVx(*vx),
+        ("LD", [B, V(vx)]) => OpCode::LdBVx(*vx),
+        ("SKP", [V(vx)]) => OpCode::Skp(*vx),
+        ("SKNP", [V(vx)]) => OpCode::Sknp(*vx),
+        ("LD", [Dt, V(vx)]) => OpCode::LdDtVx(*vx),
+        ("LD", [St, V(vx)]) => OpCode::LdStVx(*vx),
+        ("LD", [IndirectI, V(vx)]) => OpCode::LdIVx(*vx),
+        ("LD", [V(vx), IndirectI]) => OpCode::LdVxI(*vx),
+        ("ADD", [V(vx), Value(b)]) => OpCode::AddVx {
+            vx: *vx,
+            value: byte(b)?,
+        },
+        ("ADD", [V(vx), V(vy)]) => OpCode::AddVxVy { vx: *vx, vy: *vy },
+        ("ADD", [I, V(vx)]) => OpCode::AddIVx(*vx),
+        ("OR", [V(vx), V(vy)]) => OpCode::OrVxVy { vx: *vx, vy: *vy },
+        ("AND", [V(vx), V(vy)]) => OpCode::AndVxVy { vx: *vx, vy: *vy },
+        ("XOR", [V(vx), V(vy)]) => OpCode::XorVxVy { vx: *vx, vy: *vy },
+        ("SUB", [V(vx), V(vy)]) => OpCode::Sub { vx: *vx, vy: *vy },
+        ("SUBN", [V(vx), V(vy)]) => OpCode::SubN { vx: *vx, vy: *vy },
+        // with a single register VY = VX, which shifts the same way under either quirk
+        ("SHR", [V(vx)]) => OpCode::Shr { vx: *vx, vy: *vx },
+        ("SHR", [V(vx), V(vy)]) => OpCode::Shr { vx: *vx, vy: *vy },
+        ("SHL", [V(vx)]) => OpCode::Shl { vx: *vx, vy: *vx },
+        ("SHL", [V(vx), V(vy)]) => OpCode::Shl { vx: *vx, vy: *vy },
+        ("RND", [V(vx), Value(b)]) => OpCode::Rnd {
+            vx: *vx,
+            value: byte(b)?,
+        },
+        ("DRW", [V(vx), V(vy), Value(n)]) => OpCode::Drw {
+            vx: *vx,
+            vy: *vy,
+            n: value(n, 0x000F)? as usize,
+        },
+        // CHIP-8X
+        ("BG", []) => OpCode::StepBg,
+        ("ADDN", [V(vx), V(vy)]) => OpCode::AddNibbles { vx: *vx, vy: *vy },
+        ("COL", [V(vx), V(vy), Value(n)]) => OpCode::Colour {
+            vx: *vx,
+            vy: *vy,
+            n: value(n, 0x000F)? as usize,
+        },
+        ("SKP2", [V(vx)]) => OpCode::SkpKey2(*vx),
+        ("SKNP2", [V(vx)]) => OpCode::SknpKey2(*vx),
+        ("OUT", [V(vx)]) => OpCode::OutVx(*vx),
+        ("IN", [V(vx)]) => OpCode::InVx(*vx),
+        _ => bail!("invalid operands {:?}", line.operands.join(", ")),
+    };
+    Ok(op_code::encode(&op).to_be_bytes().to_vec())
+}
+
+fn operand(text: &str) -> Operand {
+    match text.to_uppercase().as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "B" => Operand::B,
+        upper => match upper.strip_prefix('V') {
+            Some(reg) if reg.len() == 1 => match usize::from_str_radix(reg, 16) {
+                Ok(vx) => Operand::V(vx),
+                Err(_) => Operand::Value(text.to_string()),
+            },
+            _ => Operand::Value(text.to_string()),
+        },
+    }
+}
+
+fn parse_number(text: &str) -> Result<u16> {
+    let lower = text.to_lowercase();
+    let parsed = if let Some(hex) = lower.strip_prefix("0x").or(lower.strip_prefix('#')) {
+        u16::from_str_radix(hex, 16)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u16::from_str_radix(bin, 2)
+    } else {
+        lower.parse()
+    };
+    parsed.map_err(|_| anyhow!("{:?} is not a number or a known label", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassemble_listing() {
+        let listing = disassemble(&[0x00, 0xE0, 0xA2, 0x2A, 0x00, 0x00, 0xFF], 0x200);
+
+        assert_eq!(
+            "0x200  00E0  CLS\n\
+             0x202  A22A  LD I, 0x22A\n\
+             0x204  0000  DW 0x0000\n\
+             0x206  FF    DB 0xFF\n",
+            listing
+        );
+    }
+
+    #[test]
+    fn assemble_source() {
+        let source = "
+            ; draws a sprite forever
+            start:  CLS
+                    LD I, sprite
+                    LD V0, 10
+                    DRW V0, V1, 2
+                    SHR v3
+                    JP start
+            sprite: DB 0b11110000, 0x90
+        ";
+
+        let rom = assemble(source, 0x200).unwrap();
+
+        assert_eq!(
+            vec![
+                0x00, 0xE0, 0xA2, 0x0C, 0x60, 0x0A, 0xD0, 0x12, 0x83, 0x36, 0x12, 0x00, 0xF0, 0x90
+            ],
+            rom
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let rom = include_bytes!("../roms/ibm.ch8");
+
+        let listing = disassemble(rom, 0x200);
+
+        assert_eq!(rom.to_vec(), assemble(&listing, 0x200).unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(assemble("LD V0, 0x100", 0x200).is_err(), "byte too large");
+        assert!(assemble("JP nowhere", 0x200).is_err(), "unknown label");
+        assert!(assemble("DRW V0, V1", 0x200).is_err(), "missing operand");
+        assert!(assemble("FOO", 0x200).is_err(), "unknown mnemonic");
+    }
+}
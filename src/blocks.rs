@@ -0,0 +1,221 @@
+//! A faster way to run frames for headless runs. Straight-line runs of instructions
+//! are decoded once into blocks, which end at the first instruction that can change
+//! the PC or write memory, and then run without being fetched again.
+//!
+//! Any write to a decoded instruction throws every block away, so self-modifying ROMs
+//! run exactly as they would one `System::step` at a time.
+
+use crate::emulator::{Fault, System};
+use crate::heap::MEMORY_SIZE;
+use crate::op_code::OpCode;
+
+// instructions in a block at most, to bound the work done decoding ahead
+const MAX_LEN: usize = 64;
+
+// instructions decoded from consecutive addresses, each with its address
+struct Block(Vec<(u16, OpCode)>);
+
+impl Block {
+    fn decode(system: &mut System, start: u16) -> Block {
+        let mut ops = Vec::new();
+        let mut addr = start;
+        while ops.len() < MAX_LEN {
+            let Some(op_code) = system.decode_at(addr) else {
+                break;
+            };
+            ops.push((addr, op_code));
+            if ends_block(&op_code) {
+                break;
+            }
+            addr = system.next_addr(addr);
+        }
+        Block(ops)
+    }
+}
+
+// whether an instruction reads or changes the PC, or writes memory which could be
+// code later in the block
+fn ends_block(op_code: &OpCode) -> bool {
+    matches!(
+        op_code,
+        OpCode::Ret
+            | OpCode::Jmp(_)
+            | OpCode::Call(_)
+            | OpCode::Se { .. }
+            | OpCode::Sne { .. }
+            | OpCode::SeVxVy { .. }
+            | OpCode::SneVxVy { .. }
+            | OpCode::JmpV0(_)
+            | OpCode::LdVxK(_)
+            | OpCode::Skp(_)
+            | OpCode::Sknp(_)
+            | OpCode::SkpKey2(_)
+            | OpCode::SknpKey2(_)
+            | OpCode::LdIVx(_)
+            | OpCode::LdBVx(_)
+    )
+}
+
+pub struct Blocks {
+    // by the address they start at
+    blocks: Vec<Option<Block>>,
+    // the system's count of code writes when the blocks were decoded
+    code_writes: u64,
+}
+
+impl Blocks {
+    pub fn new() -> Blocks {
+        Blocks {
+            blocks: (0..MEMORY_SIZE).map(|_| None).collect(),
+            code_writes: 0,
+        }
+    }
+
+    /// Runs one 60Hz frame the way `System::run_frame` does.
+    pub fn run_frame(&mut self, system: &mut System, ipf: u32) -> Result<bool, Fault> {
+        let display_wait = system.quirks().display_wait;
+        let mut changed = false;
+        let mut left = ipf;
+        'frame: while left > 0 {
+            if system.code_writes() != self.code_writes {
+                self.blocks.fill_with(|| None);
+                self.code_writes = system.code_writes();
+            }
+            let pc = system.pc();
+            let block = match self.blocks.get_mut(pc as usize) {
+                Some(slot) => slot.get_or_insert_with(|| Block::decode(system, pc)),
+                None => &mut Block(Vec::new()),
+            };
+            if block.0.is_empty() {
+                // an instruction that can't be decoded, or is past the end of memory,
+                // faults the usual way
+                system.step()?;
+                left -= 1;
+                continue;
+            }
+            for (addr, op_code) in &block.0 {
+                system.run_decoded(*addr, op_code)?;
+                left -= 1;
+                match op_code {
+                    OpCode::Drw { .. } if display_wait => {
+                        changed = true;
+                        break 'frame;
+                    }
+                    OpCode::Cls | OpCode::Drw { .. } => changed = true,
+                    _ => {}
+                }
+                if left == 0 {
+                    break;
+                }
+            }
+        }
+        system.tick_timers();
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm;
+    use crate::emulator::FrameBuffer;
+    use crate::op_code;
+    use crate::quirks::Quirks;
+    use crate::rom::Rom;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    // ADD V0, 1 / LD I, 0x207 / LD [I], V0 / LD V1, 0 / JP 0x200, where the store
+    // turns the fourth into LD V1, V0 before it runs
+    const SELF_MODIFYING: [u8; 10] = [0x70, 0x01, 0xA2, 0x07, 0xF0, 0x55, 0x61, 0x00, 0x12, 0x00];
+
+    type State = (
+        Vec<u8>,
+        [u8; 16],
+        u16,
+        u16,
+        Vec<u16>,
+        FrameBuffer,
+        u8,
+        u8,
+        u64,
+    );
+
+    fn state(system: &System) -> State {
+        (
+            system.memory().to_vec(),
+            *system.v(),
+            system.pc(),
+            system.i(),
+            system.stack().to_vec(),
+            *system.frame_buffer(),
+            system.delay_timer(),
+            system.sound_timer(),
+            system.instructions(),
+        )
+    }
+
+    // runs `rom` with and without blocks, checking they agree after every frame
+    fn differential(rom: &Rom, quirks: Quirks, frames: u64) {
+        let mut systems = [false, true].map(|blocks| {
+            let mut system = System::init(rom).unwrap();
+            system.set_quirks(quirks);
+            system.seed(7);
+            system.set_blocks(blocks);
+            system
+        });
+        for frame in 0..frames {
+            let [interpreted, blocks] = systems.each_mut().map(|system| {
+                // a different key every few frames, and none between
+                system.set_key((frame % 3 == 0).then_some((frame / 3) as u8 % 16));
+                system.run_frame(11)
+            });
+            assert_eq!(interpreted, blocks, "frame {}", frame);
+            assert!(
+                state(&systems[0]) == state(&systems[1]),
+                "state differs after frame {}",
+                frame
+            );
+            if interpreted.is_err() {
+                return;
+            }
+        }
+    }
+
+    // a ROM of random instructions that all decode
+    fn random_rom(rng: &mut StdRng) -> Rom {
+        let mut data = Vec::new();
+        while data.len() < 512 {
+            let op = rng.gen::<u16>();
+            if op_code::decode(op) != OpCode::Unknown {
+                data.extend(op.to_be_bytes());
+            }
+        }
+        Rom::new(data)
+    }
+
+    #[test]
+    fn same_as_interpreter() {
+        let ibm = Rom::new(include_bytes!("../roms/ibm.ch8").to_vec());
+        let opcodes = asm::assemble(include_str!("../tests/roms/opcodes.asm"), 0x200).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for quirks in [Quirks::default(), Quirks::vip()] {
+            differential(&ibm, quirks, 30);
+            differential(&Rom::new(opcodes.clone()), quirks, 60);
+            differential(&Rom::new(SELF_MODIFYING.to_vec()), quirks, 10);
+            for _ in 0..50 {
+                differential(&random_rom(&mut rng), quirks, 60);
+            }
+        }
+    }
+
+    #[test]
+    fn self_modifying() {
+        let mut system = System::init(&Rom::new(SELF_MODIFYING.to_vec())).unwrap();
+        system.set_blocks(true);
+
+        system.run_frame(10).unwrap();
+
+        assert_eq!(0x02, system.v()[1], "should see the second loop's store");
+    }
+}
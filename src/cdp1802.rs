@@ -0,0 +1,456 @@
+//! The RCA CDP1802, the CPU of the COSMAC VIP.
+
+use crate::emulator::Fault;
+
+/// What the CPU is wired to: memory, the I/O ports and the external flag lines.
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, value: u8);
+    /// OUT 1-7 put a byte from memory on the bus
+    fn output(&mut self, port: u8, value: u8);
+    /// INP 1-7 read a byte into memory and D
+    fn input(&mut self, port: u8) -> u8;
+    /// whether EF1-EF4 is asserted
+    fn flag(&self, n: u8) -> bool;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cpu {
+    /// the 16 scratchpad registers, any of which can be the program counter
+    pub r: [u16; 16],
+    /// which register is the program counter
+    pub p: u8,
+    /// which register addresses memory for ALU and I/O instructions
+    pub x: u8,
+    pub d: u8,
+    /// carry, or no borrow after a subtraction
+    pub df: bool,
+    /// X and P saved by an interrupt
+    pub t: u8,
+    /// interrupts enabled
+    pub ie: bool,
+    pub q: bool,
+    /// waiting in IDL for an interrupt or DMA
+    pub idle: bool,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cpu {
+    /// The state after a reset: running from R0 = 0 with interrupts enabled.
+    pub fn new() -> Cpu {
+        Cpu {
+            r: [0; 16],
+            p: 0,
+            x: 0,
+            d: 0,
+            df: false,
+            t: 0,
+            ie: true,
+            q: false,
+            idle: false,
+        }
+    }
+
+    /// Runs one instruction and returns the machine cycles it took, 3 for long
+    /// branches and skips and 2 for the rest. Does nothing while idle.
+    pub fn step(&mut self, bus: &mut impl Bus) -> Result<u32, Fault> {
+        if self.idle {
+            return Ok(1);
+        }
+        let addr = self.r[self.p as usize];
+        let op = self.fetch(bus);
+        let (i, n) = (op >> 4, op & 0x0F);
+        let rn = n as usize;
+        let rx = self.x as usize;
+        match i {
+            0x0 if n == 0 => self.idle = true,
+            0x0 => self.d = bus.read(self.r[rn]),
+            0x1 => self.r[rn] = self.r[rn].wrapping_add(1),
+            0x2 => self.r[rn] = self.r[rn].wrapping_sub(1),
+            0x3 => {
+                // 38 is SKP, the never taken branch that skips its operand
+                let taken = self.condition(n & 0x7, bus) != (n & 0x8 != 0);
+                let pc = self.r[self.p as usize];
+                // stays in the page of the operand, even if that is the last byte of one
+                self.r[self.p as usize] = match taken {
+                    true => (pc & 0xFF00) | bus.read(pc) as u16,
+                    false => pc.wrapping_add(1),
+                };
+            }
+            0x4 => {
+                self.d = bus.read(self.r[rn]);
+                self.r[rn] = self.r[rn].wrapping_add(1);
+            }
+            0x5 => bus.write(self.r[rn], self.d),
+            0x6 => match n {
+                0x0 => self.r[rx] = self.r[rx].wrapping_add(1),
+                0x1..=0x7 => {
+                    let value = bus.read(self.r[rx]);
+                    bus.output(n, value);
+                    self.r[rx] = self.r[rx].wrapping_add(1);
+                }
+                0x9..=0xF => {
+                    let value = bus.input(n & 0x7);
+                    bus.write(self.r[rx], value);
+                    self.d = value;
+                }
+                _ => {
+                    return Err(Fault::UnknownOpCode {
+                        addr,
+                        op: op.into(),
+                    })
+                }
+            },
+            0x7 => self.op_7(n, bus),
+            0x8 => self.d = self.r[rn] as u8,
+            0x9 => self.d = (self.r[rn] >> 8) as u8,
+            0xA => self.r[rn] = (self.r[rn] & 0xFF00) | self.d as u16,
+            0xB => self.r[rn] = (self.r[rn] & 0x00FF) | (self.d as u16) << 8,
+            0xC => {
+                self.long_branch(n, bus);
+                return Ok(3);
+            }
+            0xD => self.p = n,
+            0xE => self.x = n,
+            _ => self.op_f(n, bus),
+        }
+        Ok(2)
+    }
+
+    /// Takes an interrupt if they are enabled: saves X and P in T and runs R1 with R2
+    /// as X. Returns whether it was taken.
+    pub fn interrupt(&mut self) -> bool {
+        if !self.ie {
+            return false;
+        }
+        self.t = self.x << 4 | self.p;
+        self.p = 1;
+        self.x = 2;
+        self.ie = false;
+        self.idle = false;
+        true
+    }
+
+    /// A DMA out cycle: the byte at R0 for a device, with R0 moved on.
+    pub fn dma_out(&mut self, bus: &mut impl Bus) -> u8 {
+        self.idle = false;
+        let value = bus.read(self.r[0]);
+        self.r[0] = self.r[0].wrapping_add(1);
+        value
+    }
+
+    fn fetch(&mut self, bus: &mut impl Bus) -> u8 {
+        let pc = &mut self.r[self.p as usize];
+        let value = bus.read(*pc);
+        *pc = pc.wrapping_add(1);
+        value
+    }
+
+    // the short branch conditions: always, Q, D = 0, DF and EF1-4
+    fn condition(&self, n: u8, bus: &impl Bus) -> bool {
+        match n {
+            0 => true,
+            1 => self.q,
+            2 => self.d == 0,
+            3 => self.df,
+            _ => bus.flag(n - 3),
+        }
+    }
+
+    fn long_branch(&mut self, n: u8, bus: &mut impl Bus) {
+        let condition = match n & 0x3 {
+            0 => true,
+            1 => self.q,
+            2 => self.d == 0,
+            _ => self.df,
+        };
+        let pc = self.p as usize;
+        match n {
+            // LBR LBQ LBZ LBDF and their inverses
+            0x0..=0x3 | 0x8..=0xB => {
+                // C8 is LSKP, the never taken long branch
+                let taken = condition != (n & 0x8 != 0);
+                if !taken {
+                    self.r[pc] = self.r[pc].wrapping_add(2);
+                } else {
+                    let high = self.fetch(bus);
+                    let low = self.fetch(bus);
+                    self.r[pc] = u16::from_be_bytes([high, low]);
+                }
+            }
+            // NOP
+            0x4 => {}
+            // LSIE
+            0xC => {
+                if self.ie {
+                    self.r[pc] = self.r[pc].wrapping_add(2);
+                }
+            }
+            // LSNQ LSNZ LSNF, and LSQ LSZ LSDF
+            _ => {
+                if condition == (n & 0x8 != 0) {
+                    self.r[pc] = self.r[pc].wrapping_add(2);
+                }
+            }
+        }
+    }
+
+    fn op_7(&mut self, n: u8, bus: &mut impl Bus) {
+        let rx = self.x as usize;
+        match n {
+            // RET and DIS
+            0x0 | 0x1 => {
+                let value = bus.read(self.r[rx]);
+                self.r[rx] = self.r[rx].wrapping_add(1);
+                self.x = value >> 4;
+                self.p = value & 0x0F;
+                self.ie = n == 0x0;
+            }
+            0x2 => {
+                self.d = bus.read(self.r[rx]);
+                self.r[rx] = self.r[rx].wrapping_add(1);
+            }
+            0x3 => {
+                bus.write(self.r[rx], self.d);
+                self.r[rx] = self.r[rx].wrapping_sub(1);
+            }
+            0x6 => {
+                let carry = self.d & 0x01 != 0;
+                self.d = self.d >> 1 | (self.df as u8) << 7;
+                self.df = carry;
+            }
+            0x8 => bus.write(self.r[rx], self.t),
+            // MARK
+            0x9 => {
+                self.t = self.x << 4 | self.p;
+                bus.write(self.r[2], self.t);
+                self.x = self.p;
+                self.r[2] = self.r[2].wrapping_sub(1);
+            }
+            0xA => self.q = false,
+            0xB => self.q = true,
+            0xE => {
+                let carry = self.d & 0x80 != 0;
+                self.d = self.d << 1 | self.df as u8;
+                self.df = carry;
+            }
+            // ADC SDB SMB from memory, and the immediate versions
+            _ => {
+                let operand = match n {
+                    0x4 | 0x5 | 0x7 => bus.read(self.r[rx]),
+                    _ => self.fetch(bus),
+                };
+                let carry = self.df as u8;
+                match n & 0x3 {
+                    0x0 => self.add(operand, carry),
+                    0x1 => self.subtract(operand, self.d, carry),
+                    _ => self.subtract(self.d, operand, carry),
+                }
+            }
+        }
+    }
+
+    fn op_f(&mut self, n: u8, bus: &mut impl Bus) {
+        match n {
+            0x6 => {
+                self.df = self.d & 0x01 != 0;
+                self.d >>= 1;
+            }
+            0xE => {
+                self.df = self.d & 0x80 != 0;
+                self.d <<= 1;
+            }
+            _ => {
+                let operand = match n {
+                    0x0..=0x7 => bus.read(self.r[self.x as usize]),
+                    _ => self.fetch(bus),
+                };
+                match n & 0x7 {
+                    0x0 => self.d = operand,
+                    0x1 => self.d |= operand,
+                    0x2 => self.d &= operand,
+                    0x3 => self.d ^= operand,
+                    0x4 => self.add(operand, 0),
+                    0x5 => self.subtract(operand, self.d, 1),
+                    _ => self.subtract(self.d, operand, 1),
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, operand: u8, carry: u8) {
+        let sum = self.d as u16 + operand as u16 + carry as u16;
+        self.d = sum as u8;
+        self.df = sum > 0xFF;
+    }
+
+    // DF is set when there was no borrow; `carry` is 0 to borrow one more
+    fn subtract(&mut self, a: u8, b: u8, carry: u8) {
+        let difference = a as i16 - b as i16 - (1 - carry as i16);
+        self.d = difference as u8;
+        self.df = difference >= 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ram {
+        mem: Vec<u8>,
+        flags: [bool; 4],
+        output: Vec<(u8, u8)>,
+    }
+
+    impl Ram {
+        fn new(program: &[u8]) -> Ram {
+            let mut mem = vec![0; 0x10000];
+            mem[..program.len()].copy_from_slice(program);
+            Ram {
+                mem,
+                flags: [false; 4],
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Bus for Ram {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.mem[addr as usize]
+        }
+
+        fn write(&mut self, addr: u16, value: u8) {
+            self.mem[addr as usize] = value;
+        }
+
+        fn output(&mut self, port: u8, value: u8) {
+            self.output.push((port, value));
+        }
+
+        fn input(&mut self, port: u8) -> u8 {
+            port * 0x11
+        }
+
+        fn flag(&self, n: u8) -> bool {
+            self.flags[n as usize - 1]
+        }
+    }
+
+    fn run(cpu: &mut Cpu, ram: &mut Ram, steps: usize) -> u32 {
+        (0..steps).map(|_| cpu.step(ram).unwrap()).sum()
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut cpu = Cpu::new();
+        // LDI F0 / ADI 20 / ADCI 00 / SMI 02 / SDI 01
+        let mut ram = Ram::new(&[0xF8, 0xF0, 0xFC, 0x20, 0x7C, 0x00, 0xFF, 0x02, 0xFD, 0x01]);
+
+        run(&mut cpu, &mut ram, 2);
+        assert_eq!((0x10, true), (cpu.d, cpu.df), "should carry");
+        run(&mut cpu, &mut ram, 1);
+        assert_eq!(0x11, cpu.d, "should add the carry");
+        run(&mut cpu, &mut ram, 1);
+        assert_eq!((0x0F, true), (cpu.d, cpu.df), "no borrow");
+        run(&mut cpu, &mut ram, 1);
+        assert_eq!((0xF2, false), (cpu.d, cpu.df), "borrow");
+    }
+
+    #[test]
+    fn shifts() {
+        let mut cpu = Cpu::new();
+        // LDI 81 / SHR / SHLC / SHL
+        let mut ram = Ram::new(&[0xF8, 0x81, 0xF6, 0x7E, 0xFE]);
+
+        run(&mut cpu, &mut ram, 2);
+        assert_eq!((0x40, true), (cpu.d, cpu.df));
+        run(&mut cpu, &mut ram, 1);
+        assert_eq!((0x81, false), (cpu.d, cpu.df), "should shift the carry in");
+        run(&mut cpu, &mut ram, 1);
+        assert_eq!((0x02, true), (cpu.d, cpu.df));
+    }
+
+    #[test]
+    fn branches() {
+        let mut cpu = Cpu::new();
+        // 00: LDI 00 / BZ 06 / IDL / IDL
+        // 06: LBNZ 0000 / B1 0000 / LSKP / IDL / IDL / SEQ / LBQ 1234
+        let mut ram = Ram::new(&[
+            0xF8, 0x00, 0x32, 0x06, 0x00, 0x00, 0xCA, 0x00, 0x00, 0x34, 0x00, 0xC8, 0x00, 0x00,
+            0x7B, 0xC1, 0x12, 0x34,
+        ]);
+
+        let cycles = run(&mut cpu, &mut ram, 7);
+
+        assert_eq!(0x1234, cpu.r[0]);
+        assert_eq!(2 * 4 + 3 * 3, cycles, "long branches take 3 cycles");
+    }
+
+    #[test]
+    fn registers_and_memory() {
+        let mut cpu = Cpu::new();
+        // LDI 01 / PHI 3 / LDI 00 / PLO 3 / LDI 5A / STR 3 / INC 3 / LDI 00 / DEC 3 / LDN 3
+        let mut ram = Ram::new(&[
+            0xF8, 0x01, 0xB3, 0xF8, 0x00, 0xA3, 0xF8, 0x5A, 0x53, 0x13, 0xF8, 0x00, 0x23, 0x03,
+        ]);
+
+        run(&mut cpu, &mut ram, 10);
+
+        assert_eq!(0x5A, ram.mem[0x0100]);
+        assert_eq!(0x0100, cpu.r[3]);
+        assert_eq!(0x5A, cpu.d);
+    }
+
+    #[test]
+    fn subroutines_and_interrupts() {
+        let mut cpu = Cpu::new();
+        // 00: LDI 00 / PHI 2 / PHI 1 / PHI 4 / LDI F0 / PLO 2 / LDI 20 / PLO 1 / LDI 30 /
+        //     PLO 4 / SEX 2 / SEP 4 / IDL
+        // 20: interrupt: DEC 2 / SAV / REQ / RET
+        // 30: subroutine: SEQ / SEP 0
+        let mut program = vec![
+            0xF8, 0x00, 0xB2, 0xB1, 0xB4, 0xF8, 0xF0, 0xA2, 0xF8, 0x20, 0xA1, 0xF8, 0x30, 0xA4,
+            0xE2, 0xD4, 0x00,
+        ];
+        program.resize(0x20, 0);
+        program.extend([0x22, 0x78, 0x7A, 0x70]);
+        program.resize(0x30, 0);
+        program.extend([0x7B, 0xD0]);
+        let mut ram = Ram::new(&program);
+
+        run(&mut cpu, &mut ram, 15);
+        assert!(cpu.q, "subroutine should have run");
+        assert!(cpu.idle);
+
+        assert!(cpu.interrupt());
+        assert_eq!((1, 2, false), (cpu.p, cpu.x, cpu.ie));
+        run(&mut cpu, &mut ram, 4);
+        assert!(!cpu.q, "interrupt routine should have run");
+        assert_eq!((0, 2, true), (cpu.p, cpu.x, cpu.ie), "should return");
+    }
+
+    #[test]
+    fn io() {
+        let mut cpu = Cpu::new();
+        // SEX 3 / OUT 2 / INP 4 / B2 00, with 07 to output at 6
+        let mut ram = Ram::new(&[0xE3, 0x62, 0x6C, 0x35, 0x00, 0x00, 0x07]);
+        cpu.r[3] = 6;
+        ram.flags[1] = true;
+
+        run(&mut cpu, &mut ram, 4);
+
+        assert_eq!(vec![(2, 0x07)], ram.output);
+        assert_eq!(0x44, cpu.d);
+        assert_eq!(0x44, ram.mem[7]);
+        assert_eq!(0x0000, cpu.r[0], "EF2 should be taken");
+        assert_eq!(
+            Err(Fault::UnknownOpCode { addr: 0, op: 0x68 }),
+            Cpu::new().step(&mut Ram::new(&[0x68]))
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! CHIP-8X, RCA's extension of CHIP-8 for the VP-590 colour board and the VP-595
+//! sound board. The screen stays black and white underneath; colour comes from a
+//! separate layer of zones, each 8 pixels wide and 4 tall, over a background colour.
+//!
+//! Colours are the VP-590's 3-bit codes: bit 0 red, bit 1 blue and bit 2 green.
+
+use crate::theme::Rgb;
+
+pub const BLACK: u8 = 0;
+pub const RED: u8 = 1;
+pub const BLUE: u8 = 2;
+pub const GREEN: u8 = 4;
+
+/// Where the CHIP-8X interpreter loads programs, its own code taking up more room.
+pub const LOAD_ADDRESS: u16 = 0x300;
+
+/// Zones across and down the 64x32 screen.
+pub const ZONES: (usize, usize) = (8, 8);
+
+// the order 02A0 steps the background through, starting from the colour at power on
+const BACKGROUNDS: [u8; 4] = [BLUE, BLACK, GREEN, RED];
+
+/// The colour layer over the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colours {
+    /// the foreground colour of each zone, indexed [y][x]
+    pub zones: [[u8; ZONES.0]; ZONES.1],
+    background: usize,
+}
+
+impl Default for Colours {
+    // what the CHIP-8X interpreter sets up before starting a program
+    fn default() -> Colours {
+        Colours {
+            zones: [[RED; ZONES.0]; ZONES.1],
+            background: 0,
+        }
+    }
+}
+
+impl Colours {
+    pub fn background(&self) -> u8 {
+        BACKGROUNDS[self.background]
+    }
+
+    /// 02A0: the next background colour.
+    pub fn step_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUNDS.len();
+    }
+
+    /// The foreground colour of the pixel at `x`, `y`.
+    pub fn at(&self, x: usize, y: usize) -> u8 {
+        self.zones[(y / 4) % ZONES.1][(x / 8) % ZONES.0]
+    }
+
+    /// Colours the zones in columns `x` and rows `y`, both inclusive and wrapping
+    /// around the edges.
+    pub fn fill(&mut self, x: (usize, usize), y: (usize, usize), colour: u8) {
+        for row in y.0..=y.1 {
+            for column in x.0..=x.1 {
+                self.zones[row % ZONES.1][column % ZONES.0] = colour & 0b111;
+            }
+        }
+    }
+}
+
+/// How a colour looks on a TV.
+pub fn rgb(colour: u8) -> Rgb {
+    let on = |bit: u8| if colour & bit != 0 { 0xff } else { 0x00 };
+    [on(RED), on(GREEN), on(BLUE)]
+}
+
+/// 5XY1: adds each nibble separately, keeping 3 bits of each, which is how two
+/// colours and positions are combined.
+pub fn add_nibbles(x: u8, y: u8) -> u8 {
+    let low = (x & 0x07) + (y & 0x07);
+    let high = (x & 0x70) + (y & 0x70);
+    (high & 0x70) | (low & 0x07)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zones() {
+        let mut colours = Colours::default();
+
+        colours.fill((6, 9), (7, 7), GREEN | BLUE);
+
+        assert_eq!(GREEN | BLUE, colours.at(48, 28));
+        assert_eq!(GREEN | BLUE, colours.at(63, 31));
+        assert_eq!(GREEN | BLUE, colours.at(8, 31), "should wrap around");
+        assert_eq!(RED, colours.at(16, 31));
+        assert_eq!(RED, colours.at(63, 27));
+    }
+
+    #[test]
+    fn backgrounds() {
+        let mut colours = Colours::default();
+        assert_eq!(BLUE, colours.background());
+
+        for _ in 0..3 {
+            colours.step_background();
+        }
+        assert_eq!(RED, colours.background());
+        colours.step_background();
+        assert_eq!(BLUE, colours.background(), "should cycle");
+    }
+
+    #[test]
+    fn nibbles() {
+        assert_eq!(0x35, add_nibbles(0x12, 0x23));
+        assert_eq!(0x10, add_nibbles(0x75, 0x23), "carries should be dropped");
+        assert_eq!([0xff, 0x00, 0xff], rgb(RED | BLUE));
+    }
+}
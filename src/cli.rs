@@ -0,0 +1,288 @@
+use crate::config::Layer;
+use crate::screen::RenderMode;
+use chip8::{Quirks, Rom, Theme, ROM_START};
+use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "chip8", version, about = "A CHIP-8 emulator for the terminal")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // `chip8 rom.ch8` is short for `chip8 run rom.ch8`
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Play a ROM in the terminal
+    Run(RunArgs),
+    /// Print a ROM as CHIP-8 assembly
+    Disasm { rom: PathBuf },
+    /// Assemble a source file into a ROM
+    Asm {
+        source: PathBuf,
+        /// where to write the ROM, defaults to the source with a .ch8 extension
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print what is known about a ROM
+    Info { rom: PathBuf },
+    /// Run a ROM without a display and print the screen it ends on
+    Test {
+        rom: PathBuf,
+        /// number of 60Hz frames to run
+        #[arg(short, long, default_value_t = 60)]
+        frames: u64,
+        /// also record the run to an animated GIF
+        #[arg(long, value_name = "GIF")]
+        record: Option<PathBuf>,
+        #[command(flatten)]
+        emulation: EmulationArgs,
+    },
+    /// Run a ROM flat out without a display and report instructions per second
+    Bench {
+        rom: PathBuf,
+        /// instructions to run, with an optional K, M or G suffix
+        #[arg(short, long, default_value = "100M", value_parser = parse_count)]
+        cycles: u64,
+        #[command(flatten)]
+        emulation: EmulationArgs,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// ROM to play, or a directory to pick one from; without it the launcher lists
+    /// the config's rom_dir
+    pub rom: Option<PathBuf>,
+
+    /// config file to use instead of ~/.config/chip8/config.toml
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub emulation: EmulationArgs,
+
+    /// keypad layout: qwerty, azerty, dvorak, hex or 16 keys in keypad order
+    #[arg(long)]
+    pub keys: Option<String>,
+
+    /// how pixels are drawn: braille and quadrant suit small terminals, block fills the
+    /// terminal and ascii avoids Unicode
+    #[arg(long, value_parser = PossibleValuesParser::new(RenderMode::NAMES))]
+    pub render: Option<String>,
+
+    /// colour theme, adjustable with [colors] in the config file
+    #[arg(long, value_parser = PossibleValuesParser::new(Theme::NAMES))]
+    pub theme: Option<String>,
+
+    /// fixed size of the screen relative to the smallest, instead of filling the terminal
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..=8))]
+    pub scale: Option<u16>,
+
+    /// frames a pixel takes to fade out once turned off, which hides flicker
+    #[arg(long, value_name = "FRAMES")]
+    pub phosphor: Option<u8>,
+
+    /// don't ring the terminal bell for the sound timer
+    #[arg(long)]
+    pub mute: bool,
+
+    /// record the screen to an animated GIF
+    #[arg(long, value_name = "GIF")]
+    pub record: Option<PathBuf>,
+}
+
+/// Options shared by everything that runs a ROM.
+#[derive(Debug, Args)]
+pub struct EmulationArgs {
+    /// instructions per 60Hz frame
+    #[arg(long, value_name = "IPF", value_parser = clap::value_parser!(u32).range(1..))]
+    pub speed: Option<u32>,
+
+    /// vip to run each instruction as long as it took on the COSMAC VIP instead of
+    /// a fixed number per frame
+    #[arg(long, value_parser = ["ipf", "vip"])]
+    pub timing: Option<String>,
+
+    /// vip to run RCA's own interpreter on an emulated COSMAC VIP, which needs a dump
+    /// of it from --interpreter or [vip] in the config file
+    #[arg(long, value_parser = ["chip8", "vip"])]
+    pub backend: Option<String>,
+
+    /// the VIP's CHIP-8 interpreter for the vip backend
+    #[arg(long, value_name = "FILE")]
+    pub interpreter: Option<PathBuf>,
+
+    /// the VIP's monitor ROM for the vip backend
+    #[arg(long, value_name = "FILE")]
+    pub monitor: Option<PathBuf>,
+
+    #[arg(long, value_parser = PossibleValuesParser::new(Quirks::PROFILES))]
+    pub quirks: Option<String>,
+
+    /// what addresses past 0xFFF do, defaults to the quirk profile's choice
+    #[arg(long, value_parser = ["wrap", "fault"])]
+    pub addresses: Option<String>,
+
+    /// nested calls allowed before a stack overflow, 0 for no limit; defaults to the
+    /// quirk profile's, e.g. 12 on the VIP
+    #[arg(long, value_name = "CALLS")]
+    pub stack_depth: Option<usize>,
+
+    /// seed for RND so runs can be repeated
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// where to load the ROM, e.g. 0x600 for ETI-660 programs
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    pub load_address: Option<u16>,
+
+    /// where to start executing, defaults to the load address
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    pub entry: Option<u16>,
+
+    /// run straight-line code from blocks decoded ahead of time, which is faster for
+    /// batch runs and behaves the same; only with ipf timing
+    #[arg(long)]
+    pub blocks: bool,
+
+    /// run in the two-page 64x64 hi-res mode, which ROMs starting with 0x1260 get anyway
+    #[arg(long)]
+    pub hires: bool,
+}
+
+// addresses are hex with a 0x prefix, or decimal
+fn parse_address(text: &str) -> Result<u16, String> {
+    let addr = match text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => text.parse(),
+    }
+    .map_err(|_| format!("{:?} is not an address", text))?;
+    if addr > 0x0FFF {
+        return Err(format!("{:#X} is outside the 4K address space", addr));
+    }
+    Ok(addr)
+}
+
+// counts are decimal, optionally in thousands, millions or billions, e.g. 100M
+fn parse_count(text: &str) -> Result<u64, String> {
+    let (digits, scale) = match text.char_indices().last() {
+        Some((at, 'K' | 'k')) => (&text[..at], 1_000),
+        Some((at, 'M' | 'm')) => (&text[..at], 1_000_000),
+        Some((at, 'G' | 'g')) => (&text[..at], 1_000_000_000),
+        _ => (text, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|count| count.checked_mul(scale))
+        .ok_or_else(|| format!("{:?} is not a count", text))
+}
+
+impl EmulationArgs {
+    pub fn overrides(&self) -> Layer {
+        let mut layer = Layer {
+            ipf: self.speed,
+            timing: self.timing.clone(),
+            backend: self.backend.clone(),
+            quirks: self.quirks.clone(),
+            address_wrap: self.addresses.as_deref().map(|mode| mode == "wrap"),
+            stack_depth: self.stack_depth,
+            ..Layer::default()
+        };
+        layer.vip.interpreter = self.interpreter.clone();
+        layer.vip.monitor = self.monitor.clone();
+        layer
+    }
+
+    /// Applies the load address, entry point and hi-res flags to a ROM. `platform` is
+    /// where the platform loads ROMs, for those whose file doesn't say.
+    pub fn place(&self, mut rom: Rom, platform: Option<u16>) -> Rom {
+        if self.hires {
+            rom = rom.hires();
+        }
+        let platform = platform.filter(|_| rom.load_address == ROM_START);
+        if let Some(load_address) = self.load_address.or(platform) {
+            rom = rom.at(load_address);
+        }
+        if let Some(entry) = self.entry {
+            rom = rom.entry(entry);
+        }
+        rom
+    }
+}
+
+impl RunArgs {
+    /// The settings given on the command line, applied on top of the config file.
+    pub fn overrides(&self) -> Layer {
+        let mut layer = self.emulation.overrides();
+        layer.keys.layout = self.keys.clone();
+        layer.render = self.render.clone();
+        layer.scale = self.scale;
+        layer.phosphor = self.phosphor;
+        layer.colors.theme = self.theme.clone();
+        if self.mute {
+            layer.audio.enabled = Some(false);
+        }
+        layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn rom_without_subcommand() {
+        let cli = Cli::parse_from(["chip8", "--speed", "20", "roms/ibm.ch8"]);
+
+        assert!(cli.command.is_none());
+        assert_eq!(Some(PathBuf::from("roms/ibm.ch8")), cli.run.rom);
+        assert_eq!(Some(20), cli.run.overrides().ipf);
+    }
+
+    #[test]
+    fn typed_options() {
+        assert!(Cli::try_parse_from(["chip8", "--speed", "fast", "rom"]).is_err());
+        assert!(Cli::try_parse_from(["chip8", "--quirks", "nope", "rom"]).is_err());
+        assert!(Cli::try_parse_from(["chip8", "--scale", "0", "rom"]).is_err());
+        assert!(Cli::try_parse_from(["chip8", "run", "--seed", "7", "rom"]).is_ok());
+        assert!(Cli::try_parse_from(["chip8", "--load-address", "0x1000", "rom"]).is_err());
+    }
+
+    #[test]
+    fn counts() {
+        assert_eq!(Ok(100_000_000), parse_count("100M"));
+        assert_eq!(Ok(5_000), parse_count("5k"));
+        assert_eq!(Ok(42), parse_count("42"));
+        assert!(parse_count("M").is_err());
+        assert!(parse_count("1.5G").is_err());
+    }
+
+    #[test]
+    fn place_rom() {
+        let cli = Cli::parse_from(["chip8", "--load-address", "0x600", "--entry", "1538", "-"]);
+
+        let rom = cli.run.emulation.place(Rom::new(vec![]), Some(0x300));
+
+        assert_eq!(0x600, rom.load_address, "the flag should win");
+        assert_eq!(0x602, rom.entry);
+
+        let cli = Cli::parse_from(["chip8", "-"]);
+        let rom = cli.run.emulation.place(Rom::new(vec![]), Some(0x300));
+
+        assert_eq!(0x300, rom.load_address);
+        assert_eq!(0x300, rom.entry);
+    }
+}
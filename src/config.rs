@@ -0,0 +1,687 @@
+use crate::keymap::{self, Action, Hotkeys, Keymap};
+use crate::screen::RenderMode;
+use anyhow::{anyhow, bail, Context, Result};
+use chip8::{
+    chip8x,
+    database::{self, Database, Entry},
+    theme::{self, Rgb},
+    timing::Timing,
+    Quirks, Theme,
+};
+use crossterm::event::KeyCode;
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::{collections::HashMap, env, fs, path::PathBuf, str::FromStr};
+
+pub const DEFAULT_IPF: u32 = 10;
+
+/// One layer of settings. Every field is optional so a layer only overrides what it sets.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    /// instructions executed per 60Hz frame
+    pub ipf: Option<u32>,
+    /// "vip" to run as fast as the COSMAC VIP did, ignoring ipf
+    pub timing: Option<String>,
+    /// "vip" to run the original interpreter on an emulated COSMAC VIP instead of "chip8"
+    pub backend: Option<String>,
+    pub quirks: Option<String>,
+    /// wrap addresses past 0xFFF instead of faulting, whatever the quirk profile says
+    pub address_wrap: Option<bool>,
+    /// return addresses the call stack holds, 0 for no limit
+    pub stack_depth: Option<usize>,
+    /// halfblock, braille, quadrant, block or ascii
+    pub render: Option<String>,
+    /// fixed size of the screen relative to the smallest, otherwise it fills the terminal
+    pub scale: Option<u16>,
+    /// frames a pixel takes to fade out once turned off, 0 for no afterglow
+    pub phosphor: Option<u8>,
+    /// speed multiplier while turbo is held, 0 for as fast as possible
+    pub turbo: Option<u32>,
+    /// show the status bar at the start
+    pub status_bar: Option<bool>,
+    #[serde(default)]
+    pub keys: KeysLayer,
+    /// action name to host key name, e.g. `quit = "esc"`
+    #[serde(default)]
+    pub hotkeys: HashMap<String, String>,
+    #[serde(default)]
+    pub colors: ColorsLayer,
+    #[serde(default)]
+    pub audio: AudioLayer,
+    #[serde(default)]
+    pub vip: VipLayer,
+    pub save_dir: Option<PathBuf>,
+    /// where the launcher looks for ROMs
+    pub rom_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeysLayer {
+    /// a preset name or 16 host keys in keypad order
+    pub layout: Option<String>,
+    /// host key name to CHIP-8 key, applied on top of the layout
+    #[serde(default)]
+    pub bind: HashMap<String, u8>,
+    /// the same for CHIP-8X's second keypad, which is the layout with Shift held
+    #[serde(default)]
+    pub bind2: HashMap<String, u8>,
+    /// host keys the ROM database suggests, bound alongside the layout's own keys
+    #[serde(skip)]
+    pub hints: HashMap<String, u8>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorsLayer {
+    /// a named theme, which the colours below adjust
+    pub theme: Option<String>,
+    /// `#rrggbb` or a terminal colour name
+    pub foreground: Option<String>,
+    pub background: Option<String>,
+    pub border: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AudioLayer {
+    pub enabled: Option<bool>,
+}
+
+/// Images for the vip backend, which are RCA's and have to be dumped from a real VIP.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VipLayer {
+    /// the CHIP-8 interpreter, loaded at 0
+    pub interpreter: Option<PathBuf>,
+    /// the monitor ROM, whose interrupt routine the interpreter uses
+    pub monitor: Option<PathBuf>,
+}
+
+impl Layer {
+    /// Applies `other` on top of this layer.
+    pub fn merge(&mut self, other: Layer) {
+        self.ipf = other.ipf.or(self.ipf);
+        self.timing = other.timing.or(self.timing.take());
+        self.backend = other.backend.or(self.backend.take());
+        self.quirks = other.quirks.or(self.quirks.take());
+        self.address_wrap = other.address_wrap.or(self.address_wrap);
+        self.stack_depth = other.stack_depth.or(self.stack_depth);
+        self.render = other.render.or(self.render.take());
+        self.scale = other.scale.or(self.scale);
+        self.phosphor = other.phosphor.or(self.phosphor);
+        self.turbo = other.turbo.or(self.turbo);
+        self.status_bar = other.status_bar.or(self.status_bar);
+        self.keys.layout = other.keys.layout.or(self.keys.layout.take());
+        self.keys.bind.extend(other.keys.bind);
+        self.keys.bind2.extend(other.keys.bind2);
+        self.keys.hints.extend(other.keys.hints);
+        self.hotkeys.extend(other.hotkeys);
+        self.colors.theme = other.colors.theme.or(self.colors.theme.take());
+        self.colors.foreground = other.colors.foreground.or(self.colors.foreground.take());
+        self.colors.background = other.colors.background.or(self.colors.background.take());
+        self.colors.border = other.colors.border.or(self.colors.border.take());
+        self.audio.enabled = other.audio.enabled.or(self.audio.enabled);
+        self.vip.interpreter = other.vip.interpreter.or(self.vip.interpreter.take());
+        self.vip.monitor = other.vip.monitor.or(self.vip.monitor.take());
+        self.save_dir = other.save_dir.or(self.save_dir.take());
+        self.rom_dir = other.rom_dir.or(self.rom_dir.take());
+    }
+}
+
+/// The config file: global settings plus `[rom.<sha1>]` sections for individual ROMs.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub global: Layer,
+    pub rom: HashMap<String, Layer>,
+}
+
+impl Config {
+    /// Loads the config from `path`, or from the default location when there is none.
+    /// A missing default config is not an error.
+    pub fn load(path: Option<PathBuf>) -> Result<Config> {
+        let (path, required) = match path {
+            Some(path) => (path, true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        if !required && !path.exists() {
+            return Ok(Config::default());
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("could not read config {}", path.display()))?;
+        Config::parse(&text).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Config> {
+        // split off the rom sections by hand, serde's flatten would
+        // silently accept misspelt global settings
+        let mut table: toml::Table = toml::from_str(text)?;
+        let rom = match table.remove("rom") {
+            Some(rom) => rom.try_into()?,
+            None => HashMap::new(),
+        };
+        Ok(Config {
+            global: table.try_into()?,
+            rom,
+        })
+    }
+
+    /// Where the launcher looks for ROMs when none is given.
+    pub fn rom_dir(&self) -> PathBuf {
+        self.global
+            .rom_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("roms"))
+    }
+
+    /// Layers defaults, what the ROM database recommends, the global settings, the ROM's
+    /// own section and finally `overrides`.
+    pub fn settings(&self, rom_hash: &str, overrides: Layer) -> Result<Settings> {
+        let mut layer = Layer::default();
+        let mut known = Known::default();
+        if let Some(entry) = Database::embedded().lookup(rom_hash) {
+            layer = database_layer(&entry);
+            known.quirks = entry.quirks();
+            known.title = Some(entry.program.title.clone());
+        }
+        layer.merge(self.global.clone());
+        if let Some(rom) = self.rom.get(rom_hash) {
+            layer.merge(rom.clone());
+        }
+        layer.merge(overrides);
+        Settings::resolve(layer, known)
+    }
+}
+
+// what the ROM database says that doesn't fit in a layer
+#[derive(Default)]
+struct Known {
+    quirks: Option<Quirks>,
+    title: Option<String>,
+}
+
+// the database's recommendations as settings, with its key hints on the arrow keys
+fn database_layer(entry: &Entry) -> Layer {
+    let mut layer = Layer {
+        ipf: entry.rom.tickrate,
+        ..Layer::default()
+    };
+    if let Some(colors) = &entry.rom.colors {
+        layer.colors.background = colors.pixels.first().cloned();
+        layer.colors.foreground = colors.pixels.get(1).cloned();
+    }
+    for (hint, key) in &entry.rom.keys {
+        let host = match hint.as_str() {
+            "up" => "up",
+            "down" => "down",
+            "left" => "left",
+            "right" => "right",
+            "a" => "space",
+            "b" => "enter",
+            _ => continue,
+        };
+        layer.keys.hints.insert(host.to_string(), *key);
+    }
+    layer
+}
+
+/// What runs the ROM.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    /// the emulator's own CHIP-8 interpreter
+    Chip8,
+    /// RCA's interpreter running on an emulated COSMAC VIP
+    Vip {
+        interpreter: PathBuf,
+        monitor: Option<PathBuf>,
+    },
+}
+
+/// Fully resolved settings for a run.
+#[derive(Debug)]
+pub struct Settings {
+    /// the ROM's title from the database
+    pub title: Option<String>,
+    pub timing: Timing,
+    pub backend: Backend,
+    pub quirks: Quirks,
+    /// where the platform loads ROMs, if not at 0x200
+    pub load_address: Option<u16>,
+    pub render: RenderMode,
+    pub scale: Option<u16>,
+    pub phosphor: u8,
+    pub turbo: u32,
+    pub status_bar: bool,
+    pub keymap: Keymap,
+    pub hotkeys: Hotkeys,
+    pub theme: Theme,
+    pub audio: bool,
+    pub save_dir: PathBuf,
+}
+
+impl Settings {
+    fn resolve(layer: Layer, known: Known) -> Result<Settings> {
+        let mut quirks = match layer.quirks {
+            Some(name) => Quirks::profile(&name).ok_or_else(|| {
+                anyhow!(
+                    "unknown quirk profile {:?}, expected one of {}",
+                    name,
+                    Quirks::PROFILES.join(", ")
+                )
+            })?,
+            None => known.quirks.unwrap_or_default(),
+        };
+        if let Some(wrap) = layer.address_wrap {
+            quirks.address_wrap = wrap;
+        }
+        if let Some(depth) = layer.stack_depth {
+            quirks.stack_depth = Some(depth).filter(|depth| *depth > 0);
+        }
+
+        let timing = match layer.timing.as_deref() {
+            Some("vip") => Timing::Vip,
+            Some("ipf") | None => Timing::Ipf(layer.ipf.unwrap_or(DEFAULT_IPF)),
+            Some(name) => bail!("unknown timing {:?}, expected ipf or vip", name),
+        };
+
+        let backend = match layer.backend.as_deref() {
+            Some("chip8") | None => Backend::Chip8,
+            Some("vip") => Backend::Vip {
+                interpreter: layer.vip.interpreter.ok_or_else(|| {
+                    anyhow!(
+                        "the vip backend needs a dump of the VIP's CHIP-8 interpreter, which is \
+                         not included; give it with --interpreter or vip.interpreter"
+                    )
+                })?,
+                monitor: layer.vip.monitor,
+            },
+            Some(name) => bail!("unknown backend {:?}, expected chip8 or vip", name),
+        };
+
+        let render = match layer.render {
+            Some(name) => RenderMode::from_name(&name).ok_or_else(|| {
+                anyhow!(
+                    "unknown render mode {:?}, expected one of {}",
+                    name,
+                    RenderMode::NAMES.join(", ")
+                )
+            })?,
+            None => RenderMode::HalfBlock,
+        };
+
+        let mut keymap = match layer.keys.layout {
+            Some(layout) => match Keymap::preset(&layout) {
+                Some(keymap) => keymap,
+                None => Keymap::from_layout(&layout)?,
+            },
+            None => Keymap::default(),
+        };
+        for (host, key) in layer.keys.bind {
+            keymap.set(bound_key(&host, key)?, key);
+        }
+        for (host, key) in layer.keys.bind2 {
+            keymap.set2(bound_key(&host, key)?, key);
+        }
+
+        let mut hotkeys = Hotkeys::default();
+        for (action, host) in layer.hotkeys {
+            let action = Action::from_name(&action)
+                .ok_or_else(|| anyhow!("unknown hotkey action {:?}", action))?;
+            hotkeys.set(keymap::parse_key(&host)?, action);
+        }
+        // hints never take a key from the player's layout, a binding or a hotkey
+        for (host, key) in layer.keys.hints {
+            let host = bound_key(&host, key)?;
+            if keymap.key(host).is_none() && hotkeys.action(host).is_none() {
+                keymap.add(host, key);
+            }
+        }
+        // hotkeys are checked first, so the keypad key could never be pressed
+        if let Some((host, action, key)) = hotkeys.overlap(&keymap) {
+            bail!(
+                "{:?} is both the {:?} hotkey and keypad key {:X}",
+                host,
+                action,
+                key
+            );
+        }
+
+        let colors = layer.colors;
+        let mut theme = match colors.theme {
+            Some(name) => Theme::named(&name).ok_or_else(|| {
+                anyhow!(
+                    "unknown theme {:?}, expected one of {}",
+                    name,
+                    Theme::NAMES.join(", ")
+                )
+            })?,
+            None => Theme::default(),
+        };
+        if let Some(color) = colors.foreground {
+            theme.palette[1] = parse_color(&color, true)?;
+        }
+        if let Some(color) = colors.background {
+            theme.palette[0] = parse_color(&color, false)?;
+        }
+        if let Some(color) = colors.border {
+            theme.border = parse_color(&color, true)?;
+        }
+
+        Ok(Settings {
+            title: known.title,
+            timing,
+            backend,
+            load_address: quirks.chip8x.then_some(chip8x::LOAD_ADDRESS),
+            quirks,
+            render,
+            scale: layer.scale.map(|scale| scale.max(1)),
+            phosphor: layer.phosphor.unwrap_or(0),
+            turbo: layer.turbo.unwrap_or(0),
+            status_bar: layer.status_bar.unwrap_or(true),
+            keymap,
+            hotkeys,
+            theme,
+            audio: layer.audio.enabled.unwrap_or(true),
+            save_dir: layer.save_dir.unwrap_or_else(|| PathBuf::from(".")),
+        })
+    }
+}
+
+// the host key of a binding, which must be to a CHIP-8 key
+fn bound_key(host: &str, key: u8) -> Result<KeyCode> {
+    if key > 0xF {
+        bail!(
+            "{:?} is bound to {:#X}, which is not a CHIP-8 key",
+            host,
+            key
+        );
+    }
+    keymap::parse_key(host)
+}
+
+// `#rrggbb` or a terminal colour name
+fn parse_color(color: &str, foreground: bool) -> Result<Rgb> {
+    match theme::parse_rgb(color) {
+        Some(rgb) => Ok(rgb),
+        None => Color::from_str(color)
+            .map(|color| to_rgb(color, foreground))
+            .map_err(|_| anyhow!("unknown colour {:?}", color)),
+    }
+}
+
+// terminal colours as xterm draws them; `Reset` is the terminal's own colour,
+// assumed to be light text on a dark background
+fn to_rgb(color: Color, foreground: bool) -> [u8; 3] {
+    match color {
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::Reset if foreground => [0xe5, 0xe5, 0xe5],
+        Color::Reset | Color::Black => [0x00, 0x00, 0x00],
+        Color::Red => [0xcd, 0x00, 0x00],
+        Color::Green => [0x00, 0xcd, 0x00],
+        Color::Yellow => [0xcd, 0xcd, 0x00],
+        Color::Blue => [0x00, 0x00, 0xee],
+        Color::Magenta => [0xcd, 0x00, 0xcd],
+        Color::Cyan => [0x00, 0xcd, 0xcd],
+        Color::Gray => [0xe5, 0xe5, 0xe5],
+        Color::DarkGray => [0x7f, 0x7f, 0x7f],
+        Color::LightRed => [0xff, 0x00, 0x00],
+        Color::LightGreen => [0x00, 0xff, 0x00],
+        Color::LightYellow => [0xff, 0xff, 0x00],
+        Color::LightBlue => [0x5c, 0x5c, 0xff],
+        Color::LightMagenta => [0xff, 0x00, 0xff],
+        Color::LightCyan => [0x00, 0xff, 0xff],
+        Color::White => [0xff, 0xff, 0xff],
+        Color::Indexed(_) if foreground => [0xe5, 0xe5, 0xe5],
+        Color::Indexed(_) => [0x00, 0x00, 0x00],
+    }
+}
+
+/// `$XDG_CONFIG_HOME/chip8/config.toml`, falling back to `~/.config/chip8/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("chip8").join("config.toml"))
+}
+
+/// The lowercase hex SHA-1 of a ROM, used to key per-ROM sections.
+pub fn rom_hash(rom: &[u8]) -> String {
+    database::sha1(rom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r##"
+        ipf = 20
+        quirks = "vip"
+
+        [keys]
+        layout = "azerty"
+
+        [hotkeys]
+        quit = "f10"
+
+        [colors]
+        foreground = "#ffb000"
+
+        [rom.abc123]
+        ipf = 30
+
+        [rom.abc123.keys]
+        bind = { up = 0x5 }
+        bind2 = { down = 0x5 }
+    "##;
+
+    #[test]
+    fn defaults() {
+        let settings = Config::default().settings("", Layer::default()).unwrap();
+
+        assert_eq!(Timing::Ipf(DEFAULT_IPF), settings.timing);
+        assert_eq!(Backend::Chip8, settings.backend);
+        assert_eq!(Quirks::default(), settings.quirks);
+        assert_eq!(Keymap::qwerty(), settings.keymap);
+        assert_eq!(Theme::classic(), settings.theme);
+        assert_eq!(PathBuf::from("roms"), Config::default().rom_dir());
+    }
+
+    #[test]
+    fn global_settings() {
+        let config = Config::parse(CONFIG).unwrap();
+
+        let settings = config.settings("other", Layer::default()).unwrap();
+
+        assert_eq!(Timing::Ipf(20), settings.timing);
+        assert_eq!(Quirks::vip(), settings.quirks);
+        assert_eq!(Keymap::azerty(), settings.keymap);
+        assert_eq!(Some(Action::Quit), settings.hotkeys.action(KeyCode::F(10)));
+        assert_eq!(None, settings.hotkeys.action(KeyCode::Esc));
+        assert_eq!([0xff, 0xb0, 0x00], settings.theme.foreground());
+        assert_eq!(Theme::classic().background(), settings.theme.background());
+    }
+
+    #[test]
+    fn rom_section_then_overrides() {
+        let config = Config::parse(CONFIG).unwrap();
+
+        let settings = config.settings("abc123", Layer::default()).unwrap();
+
+        assert_eq!(
+            Timing::Ipf(30),
+            settings.timing,
+            "rom section should override global"
+        );
+        assert_eq!(Quirks::vip(), settings.quirks, "should keep global quirks");
+        assert_eq!(Some(0x5), settings.keymap.key(KeyCode::Up));
+        assert_eq!(Some(0x5), settings.keymap.key2(KeyCode::Down));
+
+        let overrides = Layer {
+            ipf: Some(5),
+            address_wrap: Some(false),
+            stack_depth: Some(0),
+            ..Layer::default()
+        };
+        let settings = config.settings("abc123", overrides).unwrap();
+
+        assert_eq!(
+            Timing::Ipf(5),
+            settings.timing,
+            "flags should override everything"
+        );
+        assert!(!settings.quirks.address_wrap, "should override the profile");
+        assert_eq!(None, settings.quirks.stack_depth);
+    }
+
+    #[test]
+    fn rom_database() {
+        // stars.ch8, which the database says runs at 15 ipf on the VIP
+        let hash = "0085dd8fce4f7ac2e39ba73cf67cc043f9ba4812";
+
+        let settings = Config::default().settings(hash, Layer::default()).unwrap();
+
+        assert_eq!(Some("Stars"), settings.title.as_deref());
+        assert_eq!(
+            Timing::Ipf(15),
+            settings.timing,
+            "database should override defaults"
+        );
+        assert_eq!(Quirks::vip(), settings.quirks);
+
+        let config = Config::parse("ipf = 20\nquirks = \"modern\"").unwrap();
+        let settings = config.settings(hash, Layer::default()).unwrap();
+
+        assert_eq!(
+            Timing::Ipf(20),
+            settings.timing,
+            "global should override it"
+        );
+        assert_eq!(Quirks::default(), settings.quirks);
+
+        let overrides = Layer {
+            quirks: Some("schip".to_string()),
+            ..Layer::default()
+        };
+        let settings = config.settings(hash, overrides).unwrap();
+
+        assert_eq!(Quirks::schip(), settings.quirks, "flags should override it");
+    }
+
+    #[test]
+    fn database_key_hints() {
+        let mut layer = Layer::default();
+        layer.keys.hints = HashMap::from([
+            ("up".to_string(), 0x5),
+            ("space".to_string(), 0x6),
+            ("q".to_string(), 0x7),
+        ]);
+
+        let settings = Settings::resolve(layer, Known::default()).unwrap();
+
+        assert_eq!(Some(0x5), settings.keymap.key(KeyCode::Up));
+        assert_eq!(
+            Some(0x5),
+            settings.keymap.key(KeyCode::Char('w')),
+            "the layout's key should still work"
+        );
+        assert_eq!(None, settings.keymap.key(KeyCode::Char(' ')), "a hotkey");
+        assert_eq!(Some(0x4), settings.keymap.key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn chip8x_load_address() {
+        let overrides = Layer {
+            quirks: Some("chip8x".to_string()),
+            ..Layer::default()
+        };
+
+        let settings = Config::default().settings("", overrides).unwrap();
+
+        assert_eq!(Some(0x300), settings.load_address);
+        assert_eq!(
+            None,
+            Config::default()
+                .settings("", Layer::default())
+                .unwrap()
+                .load_address
+        );
+    }
+
+    #[test]
+    fn vip_backend() {
+        let config = Config::parse("[vip]\ninterpreter = \"chip8.bin\"").unwrap();
+        let overrides = Layer {
+            backend: Some("vip".to_string()),
+            ..Layer::default()
+        };
+
+        let settings = config.settings("", overrides).unwrap();
+
+        assert_eq!(
+            Backend::Vip {
+                interpreter: PathBuf::from("chip8.bin"),
+                monitor: None
+            },
+            settings.backend
+        );
+    }
+
+    #[test]
+    fn themes() {
+        let config = Config::parse("[colors]\ntheme = \"amber\"\nbackground = \"blue\"").unwrap();
+
+        let theme = config.settings("", Layer::default()).unwrap().theme;
+
+        assert_eq!(Theme::amber().foreground(), theme.foreground());
+        assert_eq!(
+            [0x00, 0x00, 0xee],
+            theme.background(),
+            "terminal colour names"
+        );
+
+        let config = Config::parse("[colors]\ntheme = \"pink\"").unwrap();
+        assert!(config.settings("", Layer::default()).is_err());
+    }
+
+    #[test]
+    fn invalid_settings() {
+        assert!(Config::parse("speed = 3").is_err(), "unknown keys");
+
+        let config = Config::parse("quirks = \"nope\"").unwrap();
+        assert!(config.settings("", Layer::default()).is_err());
+
+        let config = Config::parse("render = \"dot\"").unwrap();
+        assert!(config.settings("", Layer::default()).is_err());
+
+        let config = Config::parse("timing = \"eti\"").unwrap();
+        assert!(config.settings("", Layer::default()).is_err());
+
+        let config = Config::parse("backend = \"vip\"").unwrap();
+        assert!(
+            config.settings("", Layer::default()).is_err(),
+            "vip needs an interpreter"
+        );
+
+        let config = Config::parse("[keys.bind]\nq = 16").unwrap();
+        assert!(config.settings("", Layer::default()).is_err());
+
+        let config = Config::parse("[hotkeys]\npause = \"q\"").unwrap();
+        assert!(
+            config.settings("", Layer::default()).is_err(),
+            "hotkey should not hide a keypad key"
+        );
+    }
+
+    #[test]
+    fn hash() {
+        assert_eq!(
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            rom_hash(&[]),
+            "sha1 of nothing"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! ROM metadata in the format of the community chip-8-database
+//! (<https://github.com/chip-8/chip-8-database>): `programs.json` lists programs with
+//! their ROMs keyed by SHA-1, and `sha1-hashes.json` maps each hash to its program.
+//! Only the three ROMs in `roms/` are embedded, not the upstream database; its files can
+//! be dropped into `data/` as is.
+
+use crate::quirks::Quirks;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::{collections::HashMap, sync::OnceLock};
+
+const PROGRAMS: &str = include_str!("../data/programs.json");
+const HASHES: &str = include_str!("../data/sha1-hashes.json");
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Program {
+    pub title: String,
+    pub description: Option<String>,
+    pub release: Option<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    pub roms: HashMap<String, RomInfo>,
+}
+
+/// What is known about one ROM of a program.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RomInfo {
+    pub file: Option<String>,
+    /// platform ids the ROM runs on, best first, e.g. `originalChip8` or `xochip`
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    /// quirks that differ from a platform's usual ones when running this ROM
+    #[serde(default)]
+    pub quirky_platforms: HashMap<String, QuirkSet>,
+    /// recommended instructions per frame
+    pub tickrate: Option<u32>,
+    pub colors: Option<Colors>,
+    /// CHIP-8 keys used for `up`, `down`, `left`, `right`, `a` and `b`
+    #[serde(default)]
+    pub keys: HashMap<String, u8>,
+}
+
+/// Quirk overrides, named as in the database.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuirkSet {
+    pub shift: Option<bool>,
+    pub memory_increment_by_x: Option<bool>,
+    pub memory_leave_i_unchanged: Option<bool>,
+    pub wrap: Option<bool>,
+    pub jump: Option<bool>,
+    pub vblank: Option<bool>,
+    pub logic: Option<bool>,
+}
+
+/// Colours as `#rrggbb`, pixels indexed by plane with the background first.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Colors {
+    #[serde(default)]
+    pub pixels: Vec<String>,
+    pub buzzer: Option<String>,
+    pub silence: Option<String>,
+}
+
+/// A ROM found in the database.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    pub program: &'a Program,
+    pub rom: &'a RomInfo,
+}
+
+impl Entry<'_> {
+    /// The first platform the ROM runs on that this emulator has quirks for, with any
+    /// quirks the ROM needs on top.
+    pub fn quirks(&self) -> Option<Quirks> {
+        self.rom.platforms.iter().find_map(|platform| {
+            let mut quirks = platform_quirks(platform)?;
+            if let Some(set) = self.rom.quirky_platforms.get(platform) {
+                set.apply(&mut quirks);
+            }
+            Some(quirks)
+        })
+    }
+}
+
+impl QuirkSet {
+    // memory_increment_by_x (SCHIP 1.0) has no equivalent here so is ignored
+    fn apply(&self, quirks: &mut Quirks) {
+        if let Some(shift) = self.shift {
+            quirks.shifting = shift;
+        }
+        if let Some(unchanged) = self.memory_leave_i_unchanged {
+            quirks.memory = !unchanged;
+        }
+        if let Some(wrap) = self.wrap {
+            quirks.clipping = !wrap;
+        }
+        if let Some(jump) = self.jump {
+            quirks.jumping = jump;
+        }
+        if let Some(vblank) = self.vblank {
+            quirks.display_wait = vblank;
+        }
+        if let Some(logic) = self.logic {
+            quirks.vf_reset = logic;
+        }
+    }
+}
+
+/// The lowercase hex SHA-1 of a ROM, which the database is keyed by.
+pub fn sha1(rom: &[u8]) -> String {
+    Sha1::digest(rom)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Quirks for a database platform id.
+pub fn platform_quirks(platform: &str) -> Option<Quirks> {
+    match platform {
+        "originalChip8" | "hybridVIP" => Some(Quirks::vip()),
+        "modernChip8" => Some(Quirks::default()),
+        "chip8x" => Some(Quirks::chip8x()),
+        "chip48" | "superchip1" | "superchip" => Some(Quirks::schip()),
+        "xochip" => Some(Quirks::xochip()),
+        _ => None,
+    }
+}
+
+pub struct Database {
+    hashes: HashMap<String, usize>,
+    programs: Vec<Program>,
+}
+
+impl Database {
+    /// Parses the contents of `programs.json` and `sha1-hashes.json`.
+    pub fn parse(programs: &str, hashes: &str) -> Result<Database> {
+        let programs: Vec<Program> =
+            serde_json::from_str(programs).context("could not parse programs.json")?;
+        let hashes: HashMap<String, usize> =
+            serde_json::from_str(hashes).context("could not parse sha1-hashes.json")?;
+        if let Some((hash, index)) = hashes.iter().find(|(_, i)| **i >= programs.len()) {
+            bail!("{} points at program {} which doesn't exist", hash, index);
+        }
+        Ok(Database { hashes, programs })
+    }
+
+    /// The database built into the emulator.
+    pub fn embedded() -> &'static Database {
+        static DATABASE: OnceLock<Database> = OnceLock::new();
+        DATABASE.get_or_init(|| Database::parse(PROGRAMS, HASHES).unwrap())
+    }
+
+    /// Looks up a ROM by the lowercase hex SHA-1 of its bytes.
+    pub fn lookup(&self, sha1: &str) -> Option<Entry<'_>> {
+        let program = &self.programs[*self.hashes.get(sha1)?];
+        let rom = program.roms.get(sha1)?;
+        Some(Entry { program, rom })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAMS: &str = r##"[
+        {
+            "title": "Pong",
+            "authors": ["Paul Vervalin"],
+            "roms": {
+                "abc": {
+                    "platforms": ["originalChip8"],
+                    "quirkyPlatforms": { "originalChip8": { "vblank": false, "shift": true } },
+                    "tickrate": 7,
+                    "colors": { "pixels": ["#000000", "#ff0000"] },
+                    "keys": { "up": 1, "down": 4 }
+                },
+                "def": { "platforms": ["megachip8", "xochip"] }
+            }
+        }
+    ]"##;
+    const HASHES: &str = r#"{ "abc": 0, "def": 0 }"#;
+
+    #[test]
+    fn lookup() {
+        let database = Database::parse(PROGRAMS, HASHES).unwrap();
+
+        let entry = database.lookup("abc").unwrap();
+
+        assert_eq!("Pong", entry.program.title);
+        assert_eq!(Some(7), entry.rom.tickrate);
+        assert_eq!(Some(&4), entry.rom.keys.get("down"));
+        assert_eq!("#ff0000", entry.rom.colors.as_ref().unwrap().pixels[1]);
+        assert!(database.lookup("123").is_none());
+    }
+
+    #[test]
+    fn quirks() {
+        let database = Database::parse(PROGRAMS, HASHES).unwrap();
+
+        let quirks = database.lookup("abc").unwrap().quirks().unwrap();
+        assert!(!quirks.display_wait, "should apply the ROM's quirks");
+        assert!(quirks.shifting);
+        assert!(quirks.vf_reset, "should keep the rest of the VIP's");
+
+        let quirks = database.lookup("def").unwrap().quirks();
+        assert_eq!(
+            Some(Quirks::xochip()),
+            quirks,
+            "should skip unknown platforms"
+        );
+    }
+
+    #[test]
+    fn bad_index() {
+        assert!(Database::parse(PROGRAMS, r#"{ "abc": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn embedded() {
+        let entry = Database::embedded()
+            .lookup("1ba58656810b67fd131eb9af3e3987863bf26c90")
+            .unwrap();
+
+        assert_eq!("IBM Logo", entry.program.title);
+    }
+}
@@ -1,4 +1,13 @@
+use crate::screen::{self, RenderMode, Screen};
+use crate::screenshot;
 use anyhow::Result;
+use chip8::{
+    chip8x::Colours,
+    emulator::HEIGHT,
+    frontend::{AudioSink, Speed, Status, VideoSink},
+    phosphor::{self, Phosphor, Shades},
+    FrameBuffer, Theme,
+};
 use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
@@ -6,77 +15,287 @@ use crossterm::{
 use ratatui::{
     layout::Flex,
     prelude::*,
-    style::Color,
-    symbols,
-    widgets::{
-        block,
-        canvas::{Canvas, Context, Rectangle},
-        Block, Borders,
-    },
+    widgets::{block, Block, Borders, Paragraph},
+};
+use std::{
+    io::{stdout, Stdout, Write},
+    path::PathBuf,
 };
-use std::io::{stdout, Stdout};
 
 pub struct Display {
     terminal: Terminal<CrosstermBackend<Stdout>>,
+    mode: RenderMode,
+    scale: Option<u16>,
+    theme: Theme,
+    save_dir: PathBuf,
+    rom_name: String,
+    phosphor: Option<Phosphor>,
+    status: Option<Status>,
+    status_bar: bool,
+    // ROM title and quirk profile shown in the status bar
+    title: String,
+    profile: String,
+    // what is on screen, kept to redraw and for screenshots
+    shades: Shades,
+    colours: Option<Colours>,
+    // rows of the screen shown, 64 in hi-res mode
+    rows: usize,
+    // terminal size at the last render, to redraw after a resize
+    size: Rect,
+    // last, so the terminal is restored after everything else is dropped
+    _guard: TerminalGuard,
 }
 
-impl Display {
-    pub fn init() -> Result<Display> {
+/// Keeps the terminal in raw mode on the alternate screen, restoring it when dropped
+/// however the program gets there.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> Result<TerminalGuard> {
         stdout().execute(EnterAlternateScreen)?;
+        // from here on, dropping it leaves the alternate screen
+        let guard = TerminalGuard;
         enable_raw_mode()?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = stdout().execute(LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+impl Display {
+    pub fn init(mode: RenderMode, scale: Option<u16>, theme: Theme) -> Result<Display> {
+        let guard = TerminalGuard::enter()?;
         let terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
-        Ok(Display { terminal })
+        Ok(Display {
+            terminal,
+            mode,
+            scale,
+            theme,
+            save_dir: PathBuf::from("."),
+            rom_name: "chip8".to_string(),
+            phosphor: None,
+            status: None,
+            status_bar: false,
+            title: String::new(),
+            profile: String::new(),
+            shades: [[0; 64]; 64],
+            colours: None,
+            rows: HEIGHT,
+            size: Rect::default(),
+            _guard: guard,
+        })
+    }
+
+    /// Where screenshots go and what they are named after.
+    pub fn screenshots(mut self, save_dir: PathBuf, rom_name: String) -> Display {
+        self.save_dir = save_dir;
+        self.rom_name = rom_name;
+        self
+    }
+
+    /// Fades pixels out over `decay` frames instead of turning them off, see
+    /// [`Phosphor`]. The screen is then redrawn every frame rather than on every draw.
+    pub fn phosphor(mut self, decay: u8) -> Display {
+        if decay > 0 {
+            self.phosphor = Some(Phosphor::new(decay));
+        }
+        self
+    }
+
+    /// Shows the top `rows` of the screen, all 64 in hi-res mode.
+    pub fn rows(mut self, rows: usize) -> Display {
+        self.rows = rows;
+        self
+    }
+
+    /// Shows a status bar under the screen with the ROM's `title`, the speed and the
+    /// quirk `profile`.
+    pub fn status_bar(mut self, visible: bool, title: &str, profile: &str) -> Display {
+        self.status_bar = visible;
+        self.title = title.to_string();
+        self.profile = profile.to_string();
+        self
+    }
+
+    // draws `shades` unless they are already on screen
+    fn show(&mut self, shades: Shades) -> Result<()> {
+        if shades == self.shades && self.terminal.size()? == self.size {
+            return Ok(());
+        }
+        self.shades = shades;
+        self.render()
+    }
+
+    /// Redraws the screen. Only the cells that changed since the last draw are written
+    /// to the terminal.
+    pub fn render(&mut self) -> Result<()> {
+        let screen = Screen::new(&self.shades, self.mode, &self.theme)
+            .colours(self.colours.as_ref())
+            .rows(self.rows);
+        let border = Style::new()
+            .fg(screen::rgb(self.theme.border))
+            .bg(screen::rgb(self.theme.background()));
+        let title = title(self.status.as_ref());
+        let status_line = match (self.status_bar, self.status.as_ref()) {
+            (true, Some(status)) => Some(status_line(status, &self.title, &self.profile)),
+            _ => None,
+        };
+        let (mode, scale, rows) = (self.mode, self.scale, self.rows);
+        let completed = self.terminal.draw(|frame| {
+            let mut size = frame.size();
+            if let Some(line) = status_line {
+                let [screen, bar] =
+                    Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(size);
+                frame.render_widget(Paragraph::new(line).style(border), bar);
+                size = screen;
+            }
+            let inner = Rect::new(
+                0,
+                0,
+                size.width.saturating_sub(2),
+                size.height.saturating_sub(2),
+            );
+            let (width, height) = mode.size(inner, scale, rows);
+            let area = centered_rect(size, width + 2, height + 2);
+            let block = Block::default()
+                .title(block::Title::from(title).alignment(Alignment::Center))
+                .borders(Borders::ALL)
+                .style(border);
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            frame.render_widget(screen, inner);
+        })?;
+        self.size = completed.area;
+        Ok(())
+    }
+}
+
+impl VideoSink for Display {
+    fn present(&mut self, frame_buffer: &FrameBuffer) -> Result<()> {
+        match self.phosphor {
+            // shown at the end of the frame once the afterglow has faded
+            Some(_) => Ok(()),
+            None => self.show(phosphor::shades(frame_buffer)),
+        }
+    }
+
+    fn end_frame(&mut self, frame_buffer: &FrameBuffer) -> Result<()> {
+        if let Some(phosphor) = self.phosphor.as_mut() {
+            let shades = *phosphor.end_frame(frame_buffer);
+            self.show(shades)?;
+        } else if self.terminal.size()? != self.size {
+            self.render()?;
+        }
+        Ok(())
+    }
+
+    fn colours(&mut self, colours: &Colours) -> Result<()> {
+        if self.colours.as_ref() == Some(colours) {
+            return Ok(());
+        }
+        self.colours = Some(*colours);
+        self.render()
+    }
+
+    fn status(&mut self, status: &Status) -> Result<()> {
+        self.status = Some(*status);
+        self.render()
+    }
+
+    fn toggle_status_bar(&mut self) -> Result<()> {
+        self.status_bar = !self.status_bar;
+        self.render()
     }
 
-    pub fn destroy() -> Result<()> {
-        stdout().execute(LeaveAlternateScreen)?;
-        disable_raw_mode()?;
+    fn screenshot(&mut self, _frame_buffer: &FrameBuffer) -> Result<()> {
+        let shades = &self.shades[..self.rows];
+        screenshot::save(shades, &self.theme, &self.save_dir, &self.rom_name)?;
         Ok(())
     }
+}
+
+/// The terminal bell as a speaker.
+pub struct Bell<W: Write = Stdout> {
+    out: W,
+    beeping: bool,
+}
 
-    pub fn render(&mut self, frame_buffer: &[[bool; 64]; 32]) {
-        self.terminal
-            .draw(|frame| {
-                let area = centered_rect(frame.size(), 128, 32);
-                frame.render_widget(
-                    Canvas::default()
-                        .marker(symbols::Marker::HalfBlock)
-                        .block(
-                            Block::default()
-                                .title(block::Title::from("CHIP-8").alignment(Alignment::Center))
-                                .borders(Borders::ALL),
-                        )
-                        .x_bounds([0.0, 64.0])
-                        .y_bounds([0.0, 32.0])
-                        .paint(|ctx| {
-                            render_frame_buffer(frame_buffer, ctx);
-                        }),
-                    area,
-                )
-            })
-            .unwrap();
+impl Default for Bell {
+    fn default() -> Bell {
+        Bell::new(stdout())
     }
 }
 
-fn render_frame_buffer(frame_buffer: &[[bool; 64]; 32], ctx: &mut Context<'_>) {
-    for (y, row) in frame_buffer.iter().enumerate() {
-        for (x, px) in row.iter().enumerate() {
-            if *px {
-                draw_pixel(ctx, x as f64, (31 - y) as f64);
-            }
+impl<W: Write> Bell<W> {
+    pub fn new(out: W) -> Bell<W> {
+        Bell {
+            out,
+            beeping: false,
         }
     }
 }
 
-fn draw_pixel(ctx: &mut Context, x: f64, y: f64) {
-    ctx.draw(&Rectangle {
-        x,
-        y,
-        width: 1.0,
-        height: 1.0,
-        color: Color::LightGreen,
-    });
+impl<W: Write> AudioSink for Bell<W> {
+    // the bell can't be held, so ring it when the tone starts
+    fn set_tone(&mut self, on: bool) -> Result<()> {
+        if on && !self.beeping {
+            self.out.write_all(b"\x07")?;
+            self.out.flush()?;
+        }
+        self.beeping = on;
+        Ok(())
+    }
 }
+
+// the speed and whether paused
+fn title(status: Option<&Status>) -> String {
+    let mut title = "CHIP-8".to_string();
+    let Some(status) = status else {
+        return title;
+    };
+    match status.speed {
+        Speed::Normal => {}
+        Speed::Turbo(0) => title.push_str(" ▶▶ max"),
+        Speed::Turbo(times) => title.push_str(&format!(" ▶▶ x{}", times)),
+        Speed::Slow(times) => title.push_str(&format!(" ▶ x1/{}", times)),
+    }
+    if status.paused {
+        title.push_str(" ‖ paused");
+    }
+    title
+}
+
+// keypad keys in the order they are laid out on the VIP
+const KEYPAD: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+fn status_line(status: &Status, title: &str, profile: &str) -> Line<'static> {
+    let mut spans = vec![Span::raw(format!(
+        " {} │ {} fps │ {} ips │ {} │ {} │ {} │ ",
+        title,
+        status.fps,
+        status.ips,
+        status.timing,
+        profile,
+        if status.sound { "🔊" } else { "🔈" },
+    ))];
+    // the keypad with the held key lit up
+    for key in KEYPAD {
+        let span = Span::raw(format!("{:X}", key));
+        spans.push(match status.key == Some(key) {
+            true => span.reversed(),
+            false => span.dim(),
+        });
+    }
+    Line::from(spans)
+}
+
 fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
     let horizontal = Layout::horizontal([width]).flex(Flex::Center);
     let vertical = Layout::vertical([height]).flex(Flex::Center);
@@ -84,3 +303,62 @@ fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
     let [area] = horizontal.areas(area);
     area
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8::timing::Timing;
+
+    fn status() -> Status {
+        Status {
+            paused: false,
+            speed: Speed::Normal,
+            fps: 60,
+            ips: 600,
+            timing: Timing::Ipf(10),
+            sound: false,
+            key: Some(0x5),
+        }
+    }
+
+    #[test]
+    fn bell() {
+        let mut bell = Bell::new(Vec::new());
+
+        for on in [false, true, true, true, false, true] {
+            bell.set_tone(on).unwrap();
+        }
+
+        assert_eq!(b"\x07\x07", &bell.out[..], "should ring once per tone");
+    }
+
+    #[test]
+    fn titles() {
+        assert_eq!("CHIP-8", title(None));
+        assert_eq!("CHIP-8", title(Some(&status())));
+
+        let status = Status {
+            paused: true,
+            speed: Speed::Slow(4),
+            ..status()
+        };
+        assert_eq!("CHIP-8 ▶ x1/4 ‖ paused", title(Some(&status)));
+    }
+
+    #[test]
+    fn status_bar() {
+        let line = status_line(&status(), "ibm", "vip");
+
+        let text: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(
+            " ibm │ 60 fps │ 600 ips │ 10 ipf │ vip │ 🔈 │ 123C456D789EA0BF",
+            text
+        );
+        let held = line.spans.iter().find(|span| span.content == "5").unwrap();
+        assert!(held.style.add_modifier.contains(Modifier::REVERSED));
+    }
+}
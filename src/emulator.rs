@@ -1,53 +1,410 @@
-use crate::heap;
-use crate::heap::Heap;
-use crate::op_code::OpCode;
+use crate::blocks::Blocks;
+use crate::chip8x::{self, Colours};
+use crate::database::{self, Database};
+use crate::heap::{self, Heap, MEMORY_SIZE};
+use crate::op_code::{self, OpCode};
+use crate::quirks::Quirks;
+use crate::rom::Rom;
+use crate::timing;
+use anyhow::Result;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::fmt;
+
+/// The screen, indexed [y][x] with the top left at [0][0]. Only the top `HEIGHT` rows
+/// are used except in hi-res mode, which uses all 64.
+pub type FrameBuffer = [[bool; 64]; 64];
+
+/// Rows on the screen normally, and in the two-page hi-res mode.
+pub const HEIGHT: usize = 32;
+pub const HIRES_HEIGHT: usize = 64;
+
+/// Why the system stopped running.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    UnknownOpCode {
+        addr: u16,
+        op: u16,
+    },
+    /// the instruction at `addr` used memory past 0xFFF without the address wrap quirk
+    AddressOutOfRange {
+        addr: u16,
+        target: u32,
+    },
+    /// the call at `addr` needed more than `depth` return addresses
+    StackOverflow {
+        addr: u16,
+        depth: usize,
+    },
+    /// the return at `addr` had nowhere to return to
+    StackUnderflow {
+        addr: u16,
+    },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Fault::UnknownOpCode { addr, op } => {
+                write!(f, "invalid opcode {:#06X} at {:#05X}", op, addr)
+            }
+            Fault::AddressOutOfRange { addr, target } => {
+                write!(f, "address {:#X} out of range at {:#05X}", target, addr)
+            }
+            Fault::StackOverflow { addr, depth } => {
+                write!(
+                    f,
+                    "stack overflow at {:#05X}, calls nested deeper than {}",
+                    addr, depth
+                )
+            }
+            Fault::StackUnderflow { addr } => {
+                write!(f, "return with an empty stack at {:#05X}", addr)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
 
 pub struct System {
     heap: Heap,
     pc: u16,
+    // where the instruction being executed was fetched from, for faults
+    op_addr: u16,
     i: u16,
-    stack: [u16; 64],
-    sp: usize,
-    pub frame_buffer: [[bool; 64]; 32], //indexed [y][x]; top left [0][0]; bottom right [31, 63]
+    // return addresses, innermost last
+    stack: Vec<u16>,
+    frame_buffer: FrameBuffer,
+    // two-page 64x64 mode
+    hires: bool,
     delay: u8,
     sound: u8,
     v: [u8; 16],
     key: Option<u8>,
+    // CHIP-8X's colour layer, second keypad and sound board port
+    colours: Colours,
+    key2: Option<u8>,
+    port_out: u8,
+    port_in: u8,
+    quirks: Quirks,
+    rng: StdRng,
+    // instructions executed since power on
+    instructions: u64,
+    // VIP cycles left in the frame, negative when an instruction ran into the next
+    cycles: i32,
+    // runs frames instead of stepping when set
+    blocks: Option<Blocks>,
 }
+impl Default for System {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl System {
+    /// An empty system without the font or a ROM loaded.
     pub fn new() -> System {
         System {
             heap: Heap::new(),
             pc: heap::ROM_START,
+            op_addr: heap::ROM_START,
             i: 0,
-            stack: [0; 64],
-            sp: 0,
-            frame_buffer: [[false; 64]; 32],
+            stack: Vec::new(),
+            frame_buffer: [[false; 64]; 64],
+            hires: false,
             delay: 0,
             sound: 0,
             v: [0; 16],
             key: None,
+            colours: Colours::default(),
+            key2: None,
+            port_out: 0,
+            port_in: 0,
+            quirks: Quirks::default(),
+            rng: StdRng::from_entropy(),
+            instructions: 0,
+            cycles: 0,
+            blocks: None,
         }
     }
 
-    pub fn init(rom_path: &String) -> System {
+    /// A system with the font and `rom` loaded, ready to run from the ROM's entry point.
+    pub fn init(rom: &Rom) -> Result<System> {
         let mut system = Self::new();
         system.heap.load_font();
-        system.heap.load_rom(rom_path);
-        system
+        system.heap.load_rom(rom)?;
+        system.pc = rom.entry;
+        system.hires = rom.hires;
+        Ok(system)
+    }
+
+    /// Like [`System::init`], with the quirks the ROM database gives for `rom` if it
+    /// knows it, loaded at 0x300 for CHIP-8X. How fast to run it is up to the caller,
+    /// from the entry's tickrate.
+    pub fn init_with_database(rom: &Rom) -> Result<System> {
+        let entry = Database::embedded().lookup(&database::sha1(&rom.data));
+        let Some(quirks) = entry.and_then(|entry| entry.quirks()) else {
+            return Self::init(rom);
+        };
+        let mut system = if quirks.chip8x && rom.load_address == heap::ROM_START {
+            Self::init(&rom.clone().at(chip8x::LOAD_ADDRESS))?
+        } else {
+            Self::init(rom)?
+        };
+        system.set_quirks(quirks);
+        Ok(system)
+    }
+
+    pub fn fetch(&mut self) -> Result<u16, Fault> {
+        self.op_addr = self.pc;
+        let high = self.load(self.pc, 0)?;
+        let low = self.load(self.pc, 1)?;
+        self.skip();
+        Ok(u16::from_be_bytes([high, low]))
+    }
+
+    // decodes an instruction for the current mode
+    fn decode(&self, op: u16) -> OpCode {
+        match (self.quirks.chip8x, op) {
+            // the hi-res interpreter's own clear screen
+            (_, 0x0230) if self.hires => OpCode::Cls,
+            (true, _) => op_code::decode_chip8x(op),
+            (false, _) => op_code::decode(op),
+        }
+    }
+
+    /// Fetches, decodes and executes the instruction at the PC. Instructions are only
+    /// decoded the first time they run, until memory writes change them.
+    pub fn step(&mut self) -> Result<OpCode, Fault> {
+        let op_code = match self.heap.decoded(self.pc as usize) {
+            Some(op_code) => {
+                self.op_addr = self.pc;
+                self.skip();
+                op_code
+            }
+            None => {
+                let op = self.fetch()?;
+                let op_code = self.decode(op);
+                if op_code == OpCode::Unknown {
+                    return Err(Fault::UnknownOpCode {
+                        addr: self.op_addr,
+                        op,
+                    });
+                }
+                self.heap.set_decoded(self.op_addr as usize, op_code);
+                op_code
+            }
+        };
+        self.execute(&op_code)?;
+        self.instructions += 1;
+        Ok(op_code)
+    }
+
+    // decodes the instruction at `addr` without running it, or none if it is unknown
+    // or out of range
+    pub(crate) fn decode_at(&mut self, addr: u16) -> Option<OpCode> {
+        if let Some(op_code) = self.heap.decoded(addr as usize) {
+            return Some(op_code);
+        }
+        let op = u16::from_be_bytes([self.load(addr, 0).ok()?, self.load(addr, 1).ok()?]);
+        match self.decode(op) {
+            OpCode::Unknown => None,
+            op_code => {
+                self.heap.set_decoded(addr as usize, op_code);
+                Some(op_code)
+            }
+        }
+    }
+
+    // executes an instruction decoded from `addr` as if it had just been fetched
+    pub(crate) fn run_decoded(&mut self, addr: u16, op_code: &OpCode) -> Result<(), Fault> {
+        self.op_addr = addr;
+        self.pc = self.next_addr(addr);
+        self.execute(op_code)?;
+        self.instructions += 1;
+        Ok(())
+    }
+
+    pub(crate) fn code_writes(&self) -> u64 {
+        self.heap.code_writes()
+    }
+
+    /// Runs `run_frame` on blocks of pre-decoded instructions, which is faster and
+    /// gives the same results.
+    pub fn set_blocks(&mut self, on: bool) {
+        self.blocks = on.then(Blocks::new);
+    }
+
+    /// Runs one 60Hz frame: `ipf` instructions, then a timer tick. With the display wait
+    /// quirk a draw ends the frame early. Returns whether the screen changed.
+    pub fn run_frame(&mut self, ipf: u32) -> Result<bool, Fault> {
+        if let Some(mut blocks) = self.blocks.take() {
+            let changed = blocks.run_frame(self, ipf);
+            self.blocks = Some(blocks);
+            return changed;
+        }
+        let mut changed = false;
+        for _ in 0..ipf {
+            match self.step()? {
+                OpCode::Drw { .. } if self.quirks.display_wait => {
+                    changed = true;
+                    break;
+                }
+                OpCode::Cls | OpCode::Drw { .. } => changed = true,
+                _ => {}
+            }
+        }
+        self.tick_timers();
+        Ok(changed)
+    }
+
+    /// Runs one 60Hz frame as the COSMAC VIP would: instructions until their cycles use
+    /// up the time between display interrupts, then a timer tick. An instruction that
+    /// runs past the interrupt delays the next frame's instructions. Draws wait for the
+    /// interrupt with the display wait quirk. Returns whether the screen changed.
+    pub fn run_vip_frame(&mut self) -> Result<bool, Fault> {
+        let mut changed = false;
+        self.cycles += timing::vip_budget();
+        while self.cycles > 0 {
+            // DRW VF reads its position before the collision flag overwrites it
+            let v = self.v;
+            let op = self.step()?;
+            self.cycles -= timing::vip_cycles(&op, &v, self) as i32;
+            match op {
+                OpCode::Drw { .. } if self.quirks.display_wait => {
+                    changed = true;
+                    // the rest of the frame is spent waiting
+                    self.cycles = self.cycles.min(0);
+                    break;
+                }
+                OpCode::Cls | OpCode::Drw { .. } => changed = true,
+                _ => {}
+            }
+        }
+        self.tick_timers();
+        Ok(changed)
     }
 
-    pub fn fetch(&mut self) -> u16 {
-        let op = self.heap.fetch_op(self.pc as usize);
-        self.pc += 2;
-        op
+    /// How many instructions have been executed, for measuring speed.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
     }
 
+    /// Sets the key currently held down, if any.
     pub fn set_key(&mut self, key: Option<u8>) {
         self.key = key;
     }
 
-    pub fn execute(&mut self, op: &OpCode) {
+    pub fn key(&self) -> Option<u8> {
+        self.key
+    }
+
+    pub fn frame_buffer(&self) -> &FrameBuffer {
+        &self.frame_buffer
+    }
+
+    /// How many rows of the frame buffer are on screen.
+    pub fn height(&self) -> usize {
+        match self.hires {
+            true => HIRES_HEIGHT,
+            false => HEIGHT,
+        }
+    }
+
+    /// CHIP-8X's colours, which only change with the chip8x quirk.
+    pub fn colours(&self) -> &Colours {
+        &self.colours
+    }
+
+    /// Sets the key held down on CHIP-8X's second keypad, if any.
+    pub fn set_key2(&mut self, key: Option<u8>) {
+        self.key2 = key;
+    }
+
+    /// The last value FXF8 sent to the sound board, which sets its pitch.
+    pub fn port_out(&self) -> u8 {
+        self.port_out
+    }
+
+    /// Sets what FXFB reads from the input port. Nothing is plugged into it by default.
+    pub fn set_port_in(&mut self, value: u8) {
+        self.port_in = value;
+    }
+
+    /// All 4K of memory.
+    pub fn memory(&self) -> &[u8] {
+        self.heap.bytes()
+    }
+
+    /// Reads memory, with `addr` wrapped to 12 bits.
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        self.heap.fetch_byte(addr as usize % MEMORY_SIZE)
+    }
+
+    /// Writes memory, with `addr` wrapped to 12 bits.
+    pub fn write_byte(&mut self, addr: u16, value: u8) {
+        self.heap.set_byte(addr as usize % MEMORY_SIZE, value);
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn set_i(&mut self, i: u16) {
+        self.i = i;
+    }
+
+    /// The return addresses of the calls in progress, outermost first.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    /// The registers V0-VF.
+    pub fn v(&self) -> &[u8; 16] {
+        &self.v
+    }
+
+    pub fn set_v(&mut self, vx: usize, value: u8) {
+        self.v[vx] = value;
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        // CHIP-8X decodes some instructions differently
+        self.heap.clear_decoded();
+    }
+
+    // makes RND repeatable
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn sound_active(&self) -> bool {
+        self.sound > 0
+    }
+
+    // called at 60Hz
+    pub fn tick_timers(&mut self) {
         if self.delay > 0 {
             self.delay -= 1;
         }
@@ -55,103 +412,137 @@ impl System {
         if self.sound > 0 {
             self.sound -= 1;
         }
+    }
 
+    pub fn execute(&mut self, op: &OpCode) -> Result<(), Fault> {
         match *op {
-            OpCode::Cls => self.frame_buffer = [[false; 64]; 32],
+            OpCode::Cls => self.frame_buffer = [[false; 64]; 64],
             OpCode::Ret => {
-                self.pc = self.stack[self.sp];
-                self.sp -= 1;
+                self.pc = self
+                    .stack
+                    .pop()
+                    .ok_or(Fault::StackUnderflow { addr: self.op_addr })?;
             }
             OpCode::Jmp(addr) => self.pc = addr,
             OpCode::Call(addr) => {
-                self.sp += 1;
-                self.stack[self.sp] = self.pc;
+                if let Some(depth) = self.quirks.stack_depth {
+                    if self.stack.len() >= depth {
+                        return Err(Fault::StackOverflow {
+                            addr: self.op_addr,
+                            depth,
+                        });
+                    }
+                }
+                self.stack.push(self.pc);
                 self.pc = addr
             }
             OpCode::Se { vx, value } => {
                 if self.v[vx] == value {
-                    self.pc += 2;
+                    self.skip();
                 }
             }
             OpCode::Sne { vx, value } => {
                 if self.v[vx] != value {
-                    self.pc += 2;
+                    self.skip();
                 }
             }
             OpCode::SeVxVy { vx, vy } => {
                 if self.v[vx] == self.v[vy] {
-                    self.pc += 2;
+                    self.skip();
                 }
             }
             OpCode::LdVx { vx, value } => self.v[vx] = value,
             OpCode::LdVxVy { vx, vy } => self.v[vx] = self.v[vy],
-            OpCode::OrVxVy { vx, vy } => self.v[vx] |= self.v[vy],
-            OpCode::AndVxVy { vx, vy } => self.v[vx] &= self.v[vy],
-            OpCode::XorVxVy { vx, vy } => self.v[vx] ^= self.v[vy],
-            OpCode::AddVxVy { vx, vy } => self.v[vx] = self.v[vx].wrapping_add(self.v[vy]),
+            OpCode::OrVxVy { vx, vy } => {
+                self.v[vx] |= self.v[vy];
+                self.reset_vf();
+            }
+            OpCode::AndVxVy { vx, vy } => {
+                self.v[vx] &= self.v[vy];
+                self.reset_vf();
+            }
+            OpCode::XorVxVy { vx, vy } => {
+                self.v[vx] ^= self.v[vy];
+                self.reset_vf();
+            }
+            OpCode::AddVxVy { vx, vy } => {
+                let (sum, carry) = self.v[vx].overflowing_add(self.v[vy]);
+                // the flag is written last, so it wins when vx is VF
+                self.v[vx] = sum;
+                self.v[0xF] = carry as u8;
+            }
             OpCode::Sub { vx, vy } => {
                 let x = self.v[vx];
                 let y = self.v[vy];
                 self.v[vx] = x.wrapping_sub(y);
-                self.v[0x000F] = if x > y { 1 } else { 0 };
+                self.v[0x000F] = if x >= y { 1 } else { 0 };
             }
             OpCode::SubN { vx, vy } => {
                 let x = self.v[vx];
                 let y = self.v[vy];
                 self.v[vx] = y.wrapping_sub(x);
-                self.v[0x000F] = if y > x { 1 } else { 0 };
+                self.v[0x000F] = if y >= x { 1 } else { 0 };
             }
-            OpCode::Shr { vx, vy: _vy } => {
-                // this impl ignores the vy... TODO configure this for
-                // some roms that use the older version that moved vy
-                // to vx then shifted
-                let x = self.v[vx];
-                let lsd = x & 0x01;
-                self.v[0xF] = lsd;
+            OpCode::Shr { vx, vy } => {
+                // the original interpreter moved vy to vx then shifted,
+                // later ones ignore vy and shift vx in place
+                let x = self.shift_source(vx, vy);
                 self.v[vx] = x >> 1;
+                self.v[0xF] = x & 0x01;
             }
-            OpCode::Shl { vx, vy: _vy } => {
+            OpCode::Shl { vx, vy } => {
                 // same as SHR wrt impl
-                let x = self.v[vx];
-                let msd = (x & 0x8F) >> 7;
-                self.v[0xF] = msd;
+                let x = self.shift_source(vx, vy);
                 self.v[vx] = x << 1;
+                self.v[0xF] = x >> 7;
             }
             OpCode::SneVxVy { vx, vy } => {
                 let x = self.v[vx];
                 let y = self.v[vy];
 
                 if x != y {
-                    self.pc += 2
+                    self.skip();
                 }
             }
             OpCode::AddVx { vx, value } => self.v[vx] = self.v[vx].wrapping_add(value),
             OpCode::LdI(value) => self.i = value,
-            OpCode::JmpV0(value) => self.pc = self.v[0] as u16 + value,
+            OpCode::JmpV0(value) => {
+                let vx = if self.quirks.jumping {
+                    ((value & 0x0F00) >> 8) as usize
+                } else {
+                    0
+                };
+                let target = self.address(self.v[vx] as u16, value)?;
+                self.pc = target;
+            }
             OpCode::Rnd { vx, value } => {
-                let rnd: u8 = rand::random();
+                let rnd: u8 = self.rng.gen();
                 self.v[vx] = rnd & value;
             }
             OpCode::Drw { vx, vy, n } => {
-                self.update_frame_buffer(vx, vy, n);
+                self.update_frame_buffer(vx, vy, n)?;
             }
-            OpCode::AddIVx(vx) => self.i = self.i.wrapping_add(self.v[vx] as u16),
+            OpCode::AddIVx(vx) => self.i = self.add_i(self.v[vx] as u16),
             OpCode::LdIVx(vx) => {
+                // check the last address first so a fault stores nothing
+                self.address(self.i, vx as u16)?;
                 for v in 0..=vx {
-                    self.heap
-                        .set_byte(self.i.wrapping_add(v as u16).into(), self.v[v]);
+                    let addr = self.address(self.i, v as u16)?;
+                    self.heap.set_byte(addr as usize, self.v[v]);
                 }
+                self.increment_i(vx);
             }
             OpCode::LdVxI(vx) => {
                 for v in 0..=vx {
-                    self.v[v] = self.heap.fetch_byte(self.i.wrapping_add(v as u16).into());
+                    self.v[v] = self.load(self.i, v as u16)?;
                 }
+                self.increment_i(vx);
             }
             OpCode::LdVxK(vx) => {
                 if let Some(key) = self.key {
                     self.v[vx] = key
                 } else {
-                    self.pc -= 2; //loop back to current instruction to wait for key press
+                    self.pc = self.op_addr; //loop back to current instruction to wait for key press
                 }
             }
             OpCode::LdDtVx(vx) => {
@@ -160,31 +551,163 @@ impl System {
             OpCode::LdStVx(vx) => {
                 self.sound = self.v[vx];
             }
+            OpCode::Skp(vx) => {
+                if self.key == Some(self.v[vx]) {
+                    self.skip();
+                }
+            }
+            OpCode::Sknp(vx) => {
+                if self.key != Some(self.v[vx]) {
+                    self.skip();
+                }
+            }
+            OpCode::LdVxDt(vx) => self.v[vx] = self.delay,
+            // 5 bytes per digit, only the low nibble picks one
+            OpCode::LdFVx(vx) => {
+                self.i = (heap::FONT_START + 5 * (self.v[vx] & 0xF) as usize) as u16
+            }
+            OpCode::LdBVx(vx) => {
+                let value = self.v[vx];
+                // check the last address first so a fault stores nothing
+                self.address(self.i, 2)?;
+                for (offset, digit) in [value / 100, value / 10 % 10, value % 10]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let addr = self.address(self.i, offset as u16)?;
+                    self.heap.set_byte(addr as usize, digit);
+                }
+            }
+            OpCode::StepBg => self.colours.step_background(),
+            OpCode::AddNibbles { vx, vy } => {
+                self.v[vx] = chip8x::add_nibbles(self.v[vx], self.v[vy]);
+            }
+            OpCode::Colour { vx, vy, n } => {
+                // the zones under VX and VX+1, which are two nibbles each for BXY0 and
+                // the position of an N row sprite otherwise
+                let (x, y) = (self.v[vx] as usize, self.v[(vx + 1) % 16] as usize);
+                let (columns, rows) = match n {
+                    0 => (
+                        (x & 0xF, (x & 0xF) + (x >> 4)),
+                        (y & 0xF, (y & 0xF) + (y >> 4)),
+                    ),
+                    n => ((x / 8, x / 8), (y / 4, (y + n - 1) / 4)),
+                };
+                self.colours.fill(columns, rows, self.v[vy]);
+            }
+            OpCode::SkpKey2(vx) => {
+                if self.key2 == Some(self.v[vx]) {
+                    self.skip();
+                }
+            }
+            OpCode::SknpKey2(vx) => {
+                if self.key2 != Some(self.v[vx]) {
+                    self.skip();
+                }
+            }
+            OpCode::OutVx(vx) => self.port_out = self.v[vx],
+            // doesn't wait for the input strobe, as nothing would ever send it
+            OpCode::InVx(vx) => self.v[vx] = self.port_in,
             OpCode::Unknown => {}
         };
+        Ok(())
     }
 
-    fn update_frame_buffer(&mut self, vx: usize, vy: usize, sprite_rows: usize) {
+    // `base + offset` as a 12-bit address, wrapped or faulting depending on the quirk
+    fn address(&self, base: u16, offset: u16) -> Result<u16, Fault> {
+        let target = base as u32 + offset as u32;
+        match target as usize {
+            addr if addr < MEMORY_SIZE => Ok(addr as u16),
+            _ if self.quirks.address_wrap => Ok((target as usize % MEMORY_SIZE) as u16),
+            _ => Err(Fault::AddressOutOfRange {
+                addr: self.op_addr,
+                target,
+            }),
+        }
+    }
+
+    fn load(&self, base: u16, offset: u16) -> Result<u8, Fault> {
+        let addr = self.address(base, offset)?;
+        Ok(self.heap.fetch_byte(addr as usize))
+    }
+
+    // moves the PC past an instruction; without the wrap quirk it can point past the
+    // end of memory, which faults when fetched
+    fn skip(&mut self) {
+        self.pc = self.next_addr(self.pc);
+    }
+
+    pub(crate) fn next_addr(&self, addr: u16) -> u16 {
+        match self.quirks.address_wrap {
+            true => addr.wrapping_add(2) % MEMORY_SIZE as u16,
+            false => addr.saturating_add(2),
+        }
+    }
+
+    // I is a 16-bit register on the VIP but only ever holds 12-bit addresses there
+    fn add_i(&self, value: u16) -> u16 {
+        match self.quirks.address_wrap {
+            true => self.i.wrapping_add(value) % MEMORY_SIZE as u16,
+            false => self.i.saturating_add(value),
+        }
+    }
+
+    fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    fn shift_source(&self, vx: usize, vy: usize) -> u8 {
+        if self.quirks.shifting {
+            self.v[vx]
+        } else {
+            self.v[vy]
+        }
+    }
+
+    fn increment_i(&mut self, vx: usize) {
+        if self.quirks.memory {
+            self.i = self.add_i(vx as u16 + 1);
+        }
+    }
+
+    fn update_frame_buffer(
+        &mut self,
+        vx: usize,
+        vy: usize,
+        sprite_rows: usize,
+    ) -> Result<(), Fault> {
+        let height = self.height();
         let start_x = self.v[vx] % 64; // allow the start_x to wrap using modulo
-        let start_y = self.v[vy] % 32; // allow the start_y to wrap using modulo
+        let start_y = self.v[vy] as usize % height; // allow the start_y to wrap using modulo
 
-        let sprite_ref: usize = self.i.into();
+        // read the whole sprite first so a fault leaves the screen untouched
+        let mut sprite = [0; 15];
+        for (row, byte) in sprite.iter_mut().enumerate().take(sprite_rows) {
+            *byte = self.load(self.i, row as u16)?;
+        }
 
         //set collision to 0
         self.v[0x000F] = 0;
 
-        for row in 0..sprite_rows {
-            let y = start_y + row as u8;
-            let sprite_row: u8 = self.heap.fetch_byte(sprite_ref + row);
+        for (row, sprite_row) in sprite.into_iter().enumerate().take(sprite_rows) {
+            let mut y = start_y + row;
+            if !self.quirks.clipping {
+                y %= height;
+            }
             for bit_index in 0..8 {
-                let x = start_x + bit_index;
+                let mut x = start_x as usize + bit_index;
+                if !self.quirks.clipping {
+                    x %= 64;
+                }
                 // to get the current pixel we want to convert the bit at bit_index to a bool
                 // shift bits in the row to the left until the current bit is at the most significant position
                 // mask all other bits out
                 // convert to bool by != 0
-                if x < 64 && y < 32 {
+                if x < 64 && y < height {
                     let pixel = ((sprite_row << bit_index) & 0x80) != 0;
-                    let old_pixel = self.frame_buffer[y as usize][x as usize];
+                    let old_pixel = self.frame_buffer[y][x];
                     let new_pixel = old_pixel ^ pixel;
 
                     // if the current pixel collides with old_pixel, set the collision flag
@@ -192,10 +715,11 @@ impl System {
                         self.v[0xF] = 1;
                     }
 
-                    self.frame_buffer[y as usize][x as usize] = new_pixel;
+                    self.frame_buffer[y][x] = new_pixel;
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -210,22 +734,109 @@ mod tests {
         system.heap.set_byte(0x0200, 0x0000);
         system.heap.set_byte(0x0201, 0x00E0);
 
-        let result = system.fetch();
+        let result = system.fetch().unwrap();
 
         assert_eq!(result, 0x00E0);
         assert_eq!(system.pc, 0x0202);
     }
 
+    #[test]
+    fn step() {
+        let mut system = System::new();
+        system.write_byte(0x0200, 0x60);
+        system.write_byte(0x0201, 0x2A);
+
+        assert_eq!(Ok(OpCode::LdVx { vx: 0, value: 0x2A }), system.step());
+        assert_eq!(0x2A, system.v()[0]);
+        assert_eq!(
+            Err(Fault::UnknownOpCode {
+                addr: 0x0202,
+                op: 0x0000
+            }),
+            system.step()
+        );
+    }
+
+    #[test]
+    fn run_frame() {
+        let mut system = System::new();
+        // ADD V0, 1 then JP 0x200
+        for (addr, byte) in [0x70, 0x01, 0x12, 0x00].into_iter().enumerate() {
+            system.write_byte(0x0200 + addr as u16, byte);
+        }
+        system.delay = 2;
+
+        let changed = system.run_frame(10).unwrap();
+
+        assert_eq!(5, system.v()[0], "should run ipf instructions");
+        assert_eq!(1, system.delay_timer(), "should tick the timers once");
+        assert!(!changed, "nothing was drawn");
+        assert_eq!(10, system.instructions());
+    }
+
+    #[test]
+    fn tick_timers() {
+        // Fx15 and Fx18 set the timers, which count down to 0 at 60Hz
+        let mut system = System::new();
+        system.v[0x0003] = 2;
+        system.execute(&OpCode::LdDtVx(0x0003)).unwrap();
+        system.execute(&OpCode::LdStVx(0x0003)).unwrap();
+        assert!(system.sound_active());
+
+        system.tick_timers();
+        assert_eq!(1, system.delay_timer());
+        assert_eq!(1, system.sound_timer());
+        assert!(system.sound_active());
+
+        system.tick_timers();
+        system.tick_timers();
+        assert_eq!(0, system.delay_timer(), "should stop at 0");
+        assert_eq!(0, system.sound_timer());
+        assert!(!system.sound_active());
+    }
+
+    #[test]
+    fn display_wait_quirk() {
+        let mut system = System::new();
+        // DRW V0, V0, 1 / ADD V1, 1 / JP 0x200
+        let rom = [0xD0, 0x01, 0x71, 0x01, 0x12, 0x00];
+        for (addr, byte) in rom.into_iter().enumerate() {
+            system.write_byte(0x0200 + addr as u16, byte);
+        }
+
+        assert!(system.run_frame(9).unwrap());
+        assert_eq!(3, system.v()[1]);
+
+        system.set_quirks(Quirks::vip());
+        system.set_pc(0x0200);
+        system.set_v(1, 0);
+        assert!(system.run_frame(9).unwrap());
+        assert_eq!(0, system.v()[1], "should stop at the draw on the VIP");
+    }
+
+    #[test]
+    fn init() {
+        let system = System::init(&Rom::new(vec![0x00, 0xE0]).at(0x600)).unwrap();
+
+        assert_eq!(0x600, system.pc, "should start at the entry point");
+        assert_eq!(0xE0, system.read_byte(0x601));
+
+        assert!(
+            System::init(&Rom::new(vec![0; 4000])).is_err(),
+            "should refuse a ROM too large for memory"
+        );
+    }
+
     #[test]
     fn cls() {
         let mut system = System {
-            frame_buffer: [[true; 64]; 32],
+            frame_buffer: [[true; 64]; 64],
             ..System::new()
         };
 
-        system.execute(&OpCode::Cls);
+        system.execute(&OpCode::Cls).unwrap();
 
-        assert_eq!([[false; 64]; 32], system.frame_buffer);
+        assert_eq!([[false; 64]; 64], system.frame_buffer);
     }
 
     #[test]
@@ -235,23 +846,28 @@ mod tests {
         // then subtracts 1 from the stack pointer.
         let mut system = System::new();
 
-        system.stack[1] = 0x0202;
-        system.sp = 1;
+        system.stack = vec![0x0204, 0x0202];
 
-        system.execute(&OpCode::Ret);
+        system.execute(&OpCode::Ret).unwrap();
 
         assert_eq!(
             0x0202, system.pc,
             "should set the pc to the value at the top of the stack"
         );
-        assert_eq!(0, system.sp, "should decrement the sp by 1");
+        assert_eq!([0x0204], system.stack(), "should pop the stack");
+
+        system.execute(&OpCode::Ret).unwrap();
+        assert_eq!(
+            Err(Fault::StackUnderflow { addr: 0x0200 }),
+            system.execute(&OpCode::Ret)
+        );
     }
 
     #[test]
     fn jmp() {
         let mut system = System::new();
 
-        system.execute(&OpCode::Jmp(0x0555));
+        system.execute(&OpCode::Jmp(0x0555)).unwrap();
 
         assert_eq!(0x0555, system.pc);
     }
@@ -264,13 +880,38 @@ mod tests {
         // The PC is then set to nnn.
         let mut system = System::new();
 
-        system.execute(&OpCode::Call(0x0555));
+        system.execute(&OpCode::Call(0x0555)).unwrap();
 
-        assert_eq!(1, system.sp, "should increment sp");
-        assert_eq!(0x0200, system.stack[1], "should put pc on top of stack");
+        assert_eq!([0x0200], system.stack(), "should put pc on top of stack");
         assert_eq!(0x0555, system.pc);
     }
 
+    #[test]
+    fn stack_depth() {
+        let mut system = System::new();
+        system.set_quirks(Quirks::vip());
+        for _ in 0..12 {
+            system.execute(&OpCode::Call(0x0555)).unwrap();
+        }
+
+        assert_eq!(
+            Err(Fault::StackOverflow {
+                addr: 0x0200,
+                depth: 12
+            }),
+            system.execute(&OpCode::Call(0x0555))
+        );
+
+        system.set_quirks(Quirks {
+            stack_depth: None,
+            ..Quirks::vip()
+        });
+        for _ in 0..100 {
+            system.execute(&OpCode::Call(0x0555)).unwrap();
+        }
+        assert_eq!(112, system.stack().len());
+    }
+
     #[test]
     fn se() {
         // Skip next instruction if Vx = kk.
@@ -280,20 +921,24 @@ mod tests {
         let mut system = System::new();
         system.v[0x000A] = 0x00AB; //vx
 
-        system.execute(&OpCode::Se {
-            vx: 0x000A,
-            value: 0x00AB,
-        });
+        system
+            .execute(&OpCode::Se {
+                vx: 0x000A,
+                value: 0x00AB,
+            })
+            .unwrap();
 
         assert_eq!(0x0202, system.pc, "should incrment pc when vx == value");
 
         let mut system = System::new();
         system.v[0x000A] = 0x00AB; //vx
 
-        system.execute(&OpCode::Se {
-            vx: 0x000A,
-            value: 0x00AC,
-        });
+        system
+            .execute(&OpCode::Se {
+                vx: 0x000A,
+                value: 0x00AC,
+            })
+            .unwrap();
 
         assert_eq!(0x0200, system.pc, "should not incrment pc when vx != value");
     }
@@ -307,17 +952,21 @@ mod tests {
         let mut system = System::new();
         system.v[0x000A] = 0x00AB; //vx
 
-        system.execute(&OpCode::Sne {
-            vx: 0x000A,
-            value: 0x00AB,
-        });
+        system
+            .execute(&OpCode::Sne {
+                vx: 0x000A,
+                value: 0x00AB,
+            })
+            .unwrap();
 
         assert_eq!(0x0200, system.pc, "should not incrment pc when vx == value");
 
-        system.execute(&OpCode::Sne {
-            vx: 0x000A,
-            value: 0x00AD,
-        });
+        system
+            .execute(&OpCode::Sne {
+                vx: 0x000A,
+                value: 0x00AD,
+            })
+            .unwrap();
 
         assert_eq!(0x0202, system.pc, "should incrment pc when vx != value");
     }
@@ -332,10 +981,12 @@ mod tests {
         system.v[0x000A] = 0x00AB; //vx
         system.v[0x000B] = 0x00AB; //vy
 
-        system.execute(&OpCode::SeVxVy {
-            vx: 0x000A,
-            vy: 0x000B,
-        });
+        system
+            .execute(&OpCode::SeVxVy {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
 
         assert_eq!(0x0202, system.pc, "should incrment pc when vx == vy");
 
@@ -343,10 +994,12 @@ mod tests {
         system.v[0x000A] = 0x00AB; //vx
         system.v[0x000B] = 0x00AC; //vy
 
-        system.execute(&OpCode::SeVxVy {
-            vx: 0x000A,
-            vy: 0x000B,
-        });
+        system
+            .execute(&OpCode::SeVxVy {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
 
         assert_eq!(0x0200, system.pc, "should not incrment pc when vx != vy");
     }
@@ -356,10 +1009,12 @@ mod tests {
         let mut system = System::new();
         system.v[0x000A] = 0x00AB;
 
-        system.execute(&OpCode::AddVx {
-            vx: 0x000A,
-            value: 0x0001,
-        });
+        system
+            .execute(&OpCode::AddVx {
+                vx: 0x000A,
+                value: 0x0001,
+            })
+            .unwrap();
 
         assert_eq!(0x00AC, system.v[0x000A]);
     }
@@ -368,10 +1023,12 @@ mod tests {
     fn ld_vx() {
         let mut system = System::new();
 
-        system.execute(&OpCode::LdVx {
-            vx: 0x000F,
-            value: 0x0012,
-        });
+        system
+            .execute(&OpCode::LdVx {
+                vx: 0x000F,
+                value: 0x0012,
+            })
+            .unwrap();
 
         assert_eq!(0x0012, system.v[0x000F]);
     }
@@ -381,10 +1038,12 @@ mod tests {
         let mut system = System::new();
         system.v[0x000A] = 0xBE;
 
-        system.execute(&OpCode::LdVxVy {
-            vx: 0x000F,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::LdVxVy {
+                vx: 0x000F,
+                vy: 0x000A,
+            })
+            .unwrap();
 
         assert_eq!(0xBE, system.v[0x000F]);
     }
@@ -395,10 +1054,12 @@ mod tests {
         system.v[0x000F] = 0xF0;
         system.v[0x000A] = 0x0F;
 
-        system.execute(&OpCode::OrVxVy {
-            vx: 0x000F,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::OrVxVy {
+                vx: 0x000F,
+                vy: 0x000A,
+            })
+            .unwrap();
 
         assert_eq!(0xFF, system.v[0x000F]);
     }
@@ -409,10 +1070,12 @@ mod tests {
         system.v[0x000F] = 0xFF;
         system.v[0x000A] = 0x1F;
 
-        system.execute(&OpCode::AndVxVy {
-            vx: 0x000F,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::AndVxVy {
+                vx: 0x000F,
+                vy: 0x000A,
+            })
+            .unwrap();
 
         assert_eq!(0x1F, system.v[0x000F]);
     }
@@ -426,10 +1089,12 @@ mod tests {
                                  // 00010101
                                  // 0x15
 
-        system.execute(&OpCode::XorVxVy {
-            vx: 0x000F,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::XorVxVy {
+                vx: 0x000F,
+                vy: 0x000A,
+            })
+            .unwrap();
 
         assert_eq!(0x15, system.v[0x000F]);
     }
@@ -437,30 +1102,80 @@ mod tests {
     #[test]
     fn add_vx_vy() {
         let mut system = System::new();
-        system.v[0x000F] = 0x05;
+        system.v[0x000E] = 0x05;
         system.v[0x000A] = 0x01;
 
-        system.execute(&OpCode::AddVxVy {
-            vx: 0x000F,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::AddVxVy {
+                vx: 0x000E,
+                vy: 0x000A,
+            })
+            .unwrap();
+
+        assert_eq!(0x06, system.v[0x000E]);
+        assert_eq!(0x00, system.v[0x000F], "no carry");
+    }
 
-        assert_eq!(0x06, system.v[0x000F]);
+    #[test]
+    fn add_vx_vy_carry() {
+        // VF is set to 1 when the sum is more than 8 bits, and Vx keeps the low 8 bits.
+        let mut system = System::new();
+        system.v[0x0000] = 0xFF;
+        system.v[0x000A] = 0x02;
+
+        system
+            .execute(&OpCode::AddVxVy {
+                vx: 0x0000,
+                vy: 0x000A,
+            })
+            .unwrap();
+
+        assert_eq!(0x01, system.v[0x0000]);
+        assert_eq!(0x01, system.v[0x000F], "must set carry");
+    }
+
+    #[test]
+    fn add_vx_vy_into_vf() {
+        // With VF as Vx, the flag is written after the sum and replaces it.
+        let mut system = System::new();
+        system.v[0x000F] = 0x05;
+        system.v[0x000A] = 0xFF;
+
+        system
+            .execute(&OpCode::AddVxVy {
+                vx: 0x000F,
+                vy: 0x000A,
+            })
+            .unwrap();
+
+        assert_eq!(0x01, system.v[0x000F], "flag should overwrite the sum");
+
+        system.v[0x000A] = 0x01;
+        system
+            .execute(&OpCode::AddVxVy {
+                vx: 0x000F,
+                vy: 0x000A,
+            })
+            .unwrap();
+
+        assert_eq!(0x00, system.v[0x000F]);
     }
 
     #[test]
     fn sub() {
         // 8xy5 - SUB Vx, Vy
         // Set Vx = Vx - Vy, set VF = NOT borrow.
-        // If Vx > Vy, then VF is set to 1, otherwise 0. Then Vy is subtracted from Vx, and the results stored in Vx.
+        // If Vx >= Vy, then VF is set to 1, otherwise 0. Then Vy is subtracted from Vx, and the results stored in Vx.
         let mut system = System::new();
         system.v[0x000D] = 0x05; //vx
         system.v[0x000A] = 0x01; //vy
 
-        system.execute(&OpCode::Sub {
-            vx: 0x000D,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::Sub {
+                vx: 0x000D,
+                vy: 0x000A,
+            })
+            .unwrap();
 
         assert_eq!(0x04, system.v[0x000D]);
         assert_eq!(0x1, system.v[0x000F], "must set borrow bit");
@@ -469,10 +1184,12 @@ mod tests {
         system.v[0x000D] = 0x01; //vx
         system.v[0x000A] = 0x05; //vy
 
-        system.execute(&OpCode::Sub {
-            vx: 0x000D,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::Sub {
+                vx: 0x000D,
+                vy: 0x000A,
+            })
+            .unwrap();
 
         assert_eq!(0xFC, system.v[0x000D]);
         assert_eq!(0x0, system.v[0x000F], "do not set borrow bit if x > y");
@@ -481,16 +1198,18 @@ mod tests {
     #[test]
     fn subn() {
         // Set Vx = Vy - Vx, set VF = NOT borrow.
-        // If Vy > Vx, then VF is set to 1, otherwise 0. Then Vx is subtracted from Vy, and the results stored in Vx.
+        // If Vy >= Vx, then VF is set to 1, otherwise 0. Then Vx is subtracted from Vy, and the results stored in Vx.
         //
         let mut system = System::new();
         system.v[0x000D] = 0x01; //vx
         system.v[0x000A] = 0x05; //vy
 
-        system.execute(&OpCode::SubN {
-            vx: 0x000D,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::SubN {
+                vx: 0x000D,
+                vy: 0x000A,
+            })
+            .unwrap();
 
         assert_eq!(0x04, system.v[0x000D]);
         assert_eq!(0x1, system.v[0x000F], "must set borrow bit");
@@ -499,15 +1218,41 @@ mod tests {
         system.v[0x000D] = 0x05; //vx
         system.v[0x000A] = 0x01; //vy
 
-        system.execute(&OpCode::SubN {
-            vx: 0x000D,
-            vy: 0x000A,
-        });
+        system
+            .execute(&OpCode::SubN {
+                vx: 0x000D,
+                vy: 0x000A,
+            })
+            .unwrap();
 
         assert_eq!(0xFC, system.v[0x000D]);
         assert_eq!(0x0, system.v[0x000F], "do not set borrow bit if x > y");
     }
 
+    #[test]
+    fn sub_equal_operands() {
+        // Equal operands don't borrow, so VF is 1 for both 8XY5 and 8XY7.
+        for op in [
+            OpCode::Sub {
+                vx: 0x000D,
+                vy: 0x000A,
+            },
+            OpCode::SubN {
+                vx: 0x000D,
+                vy: 0x000A,
+            },
+        ] {
+            let mut system = System::new();
+            system.v[0x000D] = 0x05;
+            system.v[0x000A] = 0x05;
+
+            system.execute(&op).unwrap();
+
+            assert_eq!(0x00, system.v[0x000D], "{}", op);
+            assert_eq!(0x01, system.v[0x000F], "{}: no borrow", op);
+        }
+    }
+
     #[test]
     fn shr() {
         // If the least-significant bit of Vx is 1, then VF is set to 1, otherwise 0. Then Vx is
@@ -516,10 +1261,12 @@ mod tests {
         system.v[0x000A] = 0x05; // vx 00000101
         system.v[0x000B] = 0x01; // vy, ignored in this impl
 
-        system.execute(&OpCode::Shr {
-            vx: 0x000A,
-            vy: 0x000B,
-        });
+        system
+            .execute(&OpCode::Shr {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
 
         assert_eq!(0x02, system.v[0x000A]);
         assert_eq!(
@@ -530,10 +1277,12 @@ mod tests {
         system.v[0x000A] = 0x08; // vx 00001000
         system.v[0x000B] = 0x01; // vy, ignored in this impl
 
-        system.execute(&OpCode::Shr {
-            vx: 0x000A,
-            vy: 0x000B,
-        });
+        system
+            .execute(&OpCode::Shr {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
 
         assert_eq!(0x04, system.v[0x000A]);
         assert_eq!(
@@ -550,10 +1299,12 @@ mod tests {
         system.v[0x000A] = 0x05; // vx 00000101
         system.v[0x000B] = 0x01; // vy, ignored in this impl
 
-        system.execute(&OpCode::Shl {
-            vx: 0x000A,
-            vy: 0x000B,
-        });
+        system
+            .execute(&OpCode::Shl {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
 
         assert_eq!(0x0A, system.v[0x000A]);
         assert_eq!(
@@ -564,10 +1315,12 @@ mod tests {
         system.v[0x000A] = 0x90; // vx 10010000
         system.v[0x000B] = 0x01; // vy, ignored in this impl
 
-        system.execute(&OpCode::Shl {
-            vx: 0x000A,
-            vy: 0x000B,
-        });
+        system
+            .execute(&OpCode::Shl {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
 
         assert_eq!(0x20, system.v[0x000A]);
         assert_eq!(
@@ -576,6 +1329,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn shift_into_vf() {
+        // With VF as Vx, the flag is written after the shifted value and replaces it.
+        let mut system = System::new();
+        system.v[0x000F] = 0x05; // 00000101
+
+        system
+            .execute(&OpCode::Shr {
+                vx: 0x000F,
+                vy: 0x000F,
+            })
+            .unwrap();
+
+        assert_eq!(
+            0x01, system.v[0x000F],
+            "should be the bit shifted out, not 0x02"
+        );
+
+        system.v[0x000F] = 0x40; // 01000000
+        system
+            .execute(&OpCode::Shl {
+                vx: 0x000F,
+                vy: 0x000F,
+            })
+            .unwrap();
+
+        assert_eq!(
+            0x00, system.v[0x000F],
+            "should be the bit shifted out, not 0x80"
+        );
+    }
+
     #[test]
     fn sne_vx_vy() {
         // Skip next instruction if Vx != Vy.
@@ -584,10 +1369,12 @@ mod tests {
         system.v[0x000A] = 0x05;
         system.v[0x000B] = 0x01;
 
-        system.execute(&OpCode::SneVxVy {
-            vx: 0x000A,
-            vy: 0x000B,
-        });
+        system
+            .execute(&OpCode::SneVxVy {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
 
         assert_eq!(
             0x202, system.pc,
@@ -598,10 +1385,12 @@ mod tests {
         system.v[0x000A] = 0x90;
         system.v[0x000B] = 0x90;
 
-        system.execute(&OpCode::SneVxVy {
-            vx: 0x000A,
-            vy: 0x000B,
-        });
+        system
+            .execute(&OpCode::SneVxVy {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
 
         assert_eq!(0x200, system.pc, "should not increment PC since VX == VY");
     }
@@ -610,7 +1399,7 @@ mod tests {
     fn ldi() {
         let mut system = System::new();
 
-        system.execute(&OpCode::LdI(0x0123));
+        system.execute(&OpCode::LdI(0x0123)).unwrap();
 
         assert_eq!(0x0123, system.i);
     }
@@ -623,8 +1412,351 @@ mod tests {
         let mut system = System::new();
         system.v[0] = 0x0002;
 
-        system.execute(&OpCode::JmpV0(0x0202));
+        system.execute(&OpCode::JmpV0(0x0202)).unwrap();
 
         assert_eq!(0x0204, system.pc);
     }
+
+    #[test]
+    fn vf_reset_quirk() {
+        let mut system = System::new();
+        system.set_quirks(Quirks::vip());
+        system.v[0x000F] = 0x01;
+
+        system
+            .execute(&OpCode::OrVxVy {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
+
+        assert_eq!(
+            0x00, system.v[0x000F],
+            "logic ops should reset VF on the VIP"
+        );
+    }
+
+    #[test]
+    fn shifting_quirk() {
+        let mut system = System::new();
+        system.set_quirks(Quirks::vip());
+        system.v[0x000A] = 0x05; // vx
+        system.v[0x000B] = 0x08; // vy
+
+        system
+            .execute(&OpCode::Shr {
+                vx: 0x000A,
+                vy: 0x000B,
+            })
+            .unwrap();
+
+        assert_eq!(0x04, system.v[0x000A], "should shift vy into vx");
+        assert_eq!(0x00, system.v[0x000F]);
+    }
+
+    #[test]
+    fn memory_quirk() {
+        let mut system = System::new();
+        system.set_quirks(Quirks::vip());
+        system.i = 0x0300;
+
+        system.execute(&OpCode::LdIVx(0x0002)).unwrap();
+
+        assert_eq!(0x0303, system.i, "should leave I past the last register");
+    }
+
+    #[test]
+    fn jumping_quirk() {
+        let mut system = System::new();
+        system.set_quirks(Quirks::schip());
+        system.v[0] = 0x0010;
+        system.v[2] = 0x0002;
+
+        system.execute(&OpCode::JmpV0(0x0202)).unwrap();
+
+        assert_eq!(0x0204, system.pc, "should jump to XNN + VX");
+    }
+
+    #[test]
+    fn clipping_quirk() {
+        let mut system = System::new();
+        system.heap.set_byte(0x0300, 0xFF);
+        system.i = 0x0300;
+        system.v[0] = 60;
+        system.v[1] = 0;
+
+        system.execute(&OpCode::Drw { vx: 0, vy: 1, n: 1 }).unwrap();
+
+        assert!(system.frame_buffer[0][63]);
+        assert!(!system.frame_buffer[0][0], "should clip at the right edge");
+
+        system.set_quirks(Quirks::xochip());
+        system.execute(&OpCode::Cls).unwrap();
+        system.execute(&OpCode::Drw { vx: 0, vy: 1, n: 1 }).unwrap();
+
+        assert!(system.frame_buffer[0][63]);
+        assert!(system.frame_buffer[0][3], "should wrap to the left edge");
+    }
+
+    #[test]
+    fn fetch_at_top_of_memory() {
+        let mut system = System::new();
+        system.set_quirks(Quirks::vip());
+        // CLS split across the end and the start of memory
+        system.write_byte(0x0FFF, 0x00);
+        system.write_byte(0x0000, 0xE0);
+        system.set_pc(0x0FFF);
+
+        assert_eq!(0x00E0, system.fetch().unwrap());
+        assert_eq!(0x0001, system.pc, "should wrap to 12 bits");
+
+        system.set_quirks(Quirks::default());
+        system.set_pc(0x0FFE);
+        system.fetch().unwrap();
+        assert_eq!(
+            Err(Fault::AddressOutOfRange {
+                addr: 0x1000,
+                target: 0x1000
+            }),
+            system.step(),
+            "should fault running off the end"
+        );
+    }
+
+    #[test]
+    fn skip_at_top_of_memory() {
+        let mut system = System::new();
+        system.set_quirks(Quirks::vip());
+        system.set_pc(0x0FFE);
+        system.fetch().unwrap();
+
+        system.execute(&OpCode::Se { vx: 0, value: 0 }).unwrap();
+
+        assert_eq!(0x0002, system.pc);
+    }
+
+    #[test]
+    fn jump_past_top_of_memory() {
+        let mut system = System::new();
+        system.v[0] = 0x10;
+
+        assert_eq!(
+            Err(Fault::AddressOutOfRange {
+                addr: 0x0200,
+                target: 0x100E
+            }),
+            system.execute(&OpCode::JmpV0(0x0FFE))
+        );
+
+        system.set_quirks(Quirks::vip());
+        system.execute(&OpCode::JmpV0(0x0FFE)).unwrap();
+        assert_eq!(0x000E, system.pc);
+    }
+
+    #[test]
+    fn i_past_top_of_memory() {
+        let mut system = System::new();
+        system.set_quirks(Quirks::vip());
+        system.i = 0x0FFE;
+        system.v = [1, 2, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x20];
+
+        system.execute(&OpCode::LdIVx(2)).unwrap();
+
+        assert_eq!(
+            [1, 2, 3],
+            [
+                system.heap.fetch_byte(0x0FFE),
+                system.heap.fetch_byte(0x0FFF),
+                system.heap.fetch_byte(0x0000)
+            ]
+        );
+        assert_eq!(0x0001, system.i, "should wrap I after storing");
+
+        system.execute(&OpCode::AddIVx(0xF)).unwrap();
+        assert_eq!(0x0021, system.i);
+        system.i = 0x0FF0;
+        system.execute(&OpCode::AddIVx(0xF)).unwrap();
+        assert_eq!(0x0010, system.i);
+
+        system.set_quirks(Quirks::default());
+        system.i = 0x0FFE;
+        assert!(system.execute(&OpCode::LdIVx(2)).is_err());
+        assert!(
+            system.execute(&OpCode::LdVxI(1)).is_ok(),
+            "should reach 0xFFF"
+        );
+        assert_eq!([1, 2], system.v[..2]);
+        assert!(system.execute(&OpCode::LdVxI(2)).is_err());
+    }
+
+    #[test]
+    fn sprite_past_top_of_memory() {
+        let mut system = System::new();
+        system.heap.set_byte(0x0FFF, 0x80);
+        system.heap.set_byte(0x0000, 0x80);
+        system.i = 0x0FFF;
+
+        assert!(system.execute(&OpCode::Drw { vx: 0, vy: 0, n: 2 }).is_err());
+        assert!(
+            !system.frame_buffer[0][0],
+            "should not draw part of the sprite"
+        );
+
+        system.set_quirks(Quirks::vip());
+        system.execute(&OpCode::Drw { vx: 0, vy: 0, n: 2 }).unwrap();
+        assert!(system.frame_buffer[0][0] && system.frame_buffer[1][0]);
+    }
+
+    #[test]
+    fn skp_sknp() {
+        // Skip next instruction if the key with the value of Vx is (or is not) pressed.
+        let mut system = System::new();
+        system.v[0x0001] = 0x000E;
+        system.set_key(Some(0x000E));
+
+        system.execute(&OpCode::Skp(0x0001)).unwrap();
+        assert_eq!(0x0202, system.pc, "should skip when the key is down");
+        system.execute(&OpCode::Sknp(0x0001)).unwrap();
+        assert_eq!(0x0202, system.pc, "should not skip when the key is down");
+
+        system.set_key(None);
+        system.execute(&OpCode::Skp(0x0001)).unwrap();
+        assert_eq!(0x0202, system.pc, "should not skip when the key is up");
+        system.execute(&OpCode::Sknp(0x0001)).unwrap();
+        assert_eq!(0x0204, system.pc, "should skip when the key is up");
+    }
+
+    #[test]
+    fn ld_vx_dt() {
+        let mut system = System::new();
+        system.delay = 0x0042;
+
+        system.execute(&OpCode::LdVxDt(0x0003)).unwrap();
+
+        assert_eq!(0x0042, system.v[0x0003]);
+    }
+
+    #[test]
+    fn ld_f_vx() {
+        // Set I to the font sprite for the digit in Vx.
+        let mut system = System::new();
+        system.heap.load_font();
+        system.v[0x0002] = 0x001A;
+
+        system.execute(&OpCode::LdFVx(0x0002)).unwrap();
+
+        assert_eq!((heap::FONT_START + 5 * 0xA) as u16, system.i);
+        assert_eq!(0xF0, system.read_byte(system.i), "the top row of A");
+    }
+
+    #[test]
+    fn ld_b_vx() {
+        // Store the hundreds, tens and ones of Vx at I, I+1 and I+2.
+        let mut system = System::new();
+        system.v[0x0005] = 254;
+        system.i = 0x0300;
+
+        system.execute(&OpCode::LdBVx(0x0005)).unwrap();
+
+        assert_eq!([2, 5, 4], system.memory()[0x0300..0x0303]);
+        assert_eq!(0x0300, system.i, "should leave I alone");
+
+        system.i = 0x0FFE;
+        assert!(system.execute(&OpCode::LdBVx(0x0005)).is_err());
+        assert_eq!(
+            0,
+            system.read_byte(0x0FFE),
+            "should store nothing on a fault"
+        );
+    }
+
+    #[test]
+    fn init_with_database() {
+        let stars = Rom::new(include_bytes!("../roms/stars.ch8").to_vec());
+        let system = System::init_with_database(&stars).unwrap();
+        assert_eq!(Quirks::vip(), system.quirks);
+
+        let unknown = Rom::new(vec![0x12, 0x00]);
+        let system = System::init_with_database(&unknown).unwrap();
+        assert_eq!(Quirks::default(), system.quirks);
+    }
+
+    #[test]
+    fn self_modifying() {
+        // LD V0, 1 / ADD V0, 1 / LD I, 0x201 / LD [I], V0 / JP 0x200, where the store
+        // turns the first into LD V0, 2
+        let rom = Rom::new(vec![
+            0x60, 0x01, 0x70, 0x01, 0xA2, 0x01, 0xF0, 0x55, 0x12, 0x00,
+        ]);
+        let mut system = System::init(&rom).unwrap();
+
+        for _ in 0..5 {
+            system.step().unwrap();
+        }
+        assert_eq!(OpCode::LdVx { vx: 0, value: 2 }, system.step().unwrap());
+
+        // writing the first byte of the next instruction
+        system.write_byte(0x0202, 0x61);
+        system.step().unwrap();
+        assert_eq!(0x01, system.v[1], "should see the write before it");
+    }
+
+    #[test]
+    fn chip8x() {
+        // BG / LD V0, 0x21 / LD V1, 0x13 / LD V2, 4 / COL V0, V2, 0 / COL V1, V2, 5
+        let rom = Rom::new(vec![
+            0x02, 0xA0, 0x60, 0x21, 0x61, 0x13, 0x62, 0x04, 0xB0, 0x20, 0xB1, 0x25,
+        ]);
+        let mut system = System::init(&rom).unwrap();
+        system.set_quirks(Quirks::chip8x());
+
+        for _ in 0..6 {
+            system.step().unwrap();
+        }
+
+        let colours = system.colours();
+        assert_eq!(chip8x::BLACK, colours.background());
+        // BXY0 colours columns 1-3 of rows 3-4, with V1 as the rows
+        assert_eq!(chip8x::GREEN, colours.at(8, 12));
+        assert_eq!(chip8x::GREEN, colours.at(31, 19));
+        assert_eq!(chip8x::RED, colours.at(32, 12));
+        // BXY5 colours the zones a 5 row sprite at V1, V2 covers
+        assert_eq!(chip8x::GREEN, colours.at(16, 4));
+        assert_eq!(chip8x::RED, colours.at(16, 0));
+
+        system.v[3] = 0x4;
+        system.set_key2(Some(0x4));
+        system.pc = 0x300;
+        system.execute(&OpCode::SkpKey2(3)).unwrap();
+        assert_eq!(0x302, system.pc);
+        system.execute(&OpCode::SknpKey2(3)).unwrap();
+        assert_eq!(0x302, system.pc);
+
+        system.execute(&OpCode::OutVx(3)).unwrap();
+        assert_eq!(0x4, system.port_out());
+        system.set_port_in(0x9);
+        system.execute(&OpCode::InVx(4)).unwrap();
+        assert_eq!(0x9, system.v[4]);
+    }
+
+    #[test]
+    fn hires() {
+        // LD V0, 60 / LD I, 0x2D0 / DRW V0, V0, 1 / CLS
+        let mut data = vec![0x12, 0x60];
+        data.resize(0xC0, 0);
+        data.extend([0x60, 0x3C, 0xA2, 0xD0, 0xD0, 0x01, 0x02, 0x30]);
+        data.resize(0xD0, 0);
+        data.push(0xFF);
+        let mut system = System::init(&Rom::new(data)).unwrap();
+        assert_eq!(64, system.height());
+
+        for _ in 0..3 {
+            system.step().unwrap();
+        }
+        assert!(system.frame_buffer[60][60], "should draw below row 32");
+        assert!(!system.frame_buffer[28][60]);
+
+        system.step().unwrap();
+        assert!(!system.frame_buffer[60][60], "0230 should clear the screen");
+    }
 }
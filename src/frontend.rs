@@ -0,0 +1,593 @@
+use crate::chip8x::Colours;
+use crate::emulator::{Fault, FrameBuffer, System, HEIGHT};
+use crate::timing::Timing;
+use crate::vip::Vip;
+use anyhow::Result;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// How many times longer each frame takes in slow motion.
+pub const SLOW_MOTION: u32 = 4;
+
+/// Something the run loop can drive: `System`, or a `Vip` running the original interpreter.
+pub trait Machine {
+    /// Runs one 60Hz frame. Returns whether the screen changed.
+    fn step_frame(&mut self, timing: Timing) -> Result<bool, Fault>;
+
+    fn frame_buffer(&self) -> &FrameBuffer;
+
+    fn set_key(&mut self, key: Option<u8>);
+
+    /// Sets the key held on CHIP-8X's second keypad. Machines without one ignore it.
+    fn set_key2(&mut self, _key: Option<u8>) {}
+
+    fn key(&self) -> Option<u8>;
+
+    fn sound_active(&self) -> bool;
+
+    /// How many instructions have been executed, of whatever CPU the machine has.
+    fn instructions(&self) -> u64;
+
+    /// Return addresses of the calls in progress, if the machine keeps track of them.
+    fn stack(&self) -> &[u16] {
+        &[]
+    }
+
+    /// The colour layer over the screen, for CHIP-8X.
+    fn colours(&self) -> Option<&Colours> {
+        None
+    }
+
+    /// How many rows of the frame buffer are on screen.
+    fn height(&self) -> usize {
+        HEIGHT
+    }
+}
+
+impl Machine for System {
+    fn step_frame(&mut self, timing: Timing) -> Result<bool, Fault> {
+        timing.run_frame(self)
+    }
+
+    fn frame_buffer(&self) -> &FrameBuffer {
+        System::frame_buffer(self)
+    }
+
+    fn set_key(&mut self, key: Option<u8>) {
+        System::set_key(self, key)
+    }
+
+    fn set_key2(&mut self, key: Option<u8>) {
+        System::set_key2(self, key)
+    }
+
+    fn key(&self) -> Option<u8> {
+        System::key(self)
+    }
+
+    fn sound_active(&self) -> bool {
+        System::sound_active(self)
+    }
+
+    fn instructions(&self) -> u64 {
+        System::instructions(self)
+    }
+
+    fn stack(&self) -> &[u16] {
+        System::stack(self)
+    }
+
+    fn colours(&self) -> Option<&Colours> {
+        Some(System::colours(self)).filter(|_| self.quirks().chip8x)
+    }
+
+    fn height(&self) -> usize {
+        System::height(self)
+    }
+}
+
+// the VIP runs at its own speed whatever the timing
+impl Machine for Vip {
+    fn step_frame(&mut self, _timing: Timing) -> Result<bool, Fault> {
+        self.run_frame()
+    }
+
+    fn frame_buffer(&self) -> &FrameBuffer {
+        Vip::frame_buffer(self)
+    }
+
+    fn set_key(&mut self, key: Option<u8>) {
+        Vip::set_key(self, key)
+    }
+
+    fn key(&self) -> Option<u8> {
+        Vip::key(self)
+    }
+
+    fn sound_active(&self) -> bool {
+        Vip::sound_active(self)
+    }
+
+    fn instructions(&self) -> u64 {
+        Vip::instructions(self)
+    }
+}
+
+/// Something that shows the screen.
+pub trait VideoSink {
+    /// Called at the end of a 60Hz frame in which the screen changed, like the
+    /// vblank of a real display.
+    fn present(&mut self, frame_buffer: &FrameBuffer) -> Result<()>;
+
+    /// Called at the end of every 60Hz frame, after `present`, whether or not the
+    /// screen changed.
+    fn end_frame(&mut self, _frame_buffer: &FrameBuffer) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before `present` every frame with the CHIP-8X colours, if the machine
+    /// has them.
+    fn colours(&mut self, _colours: &Colours) -> Result<()> {
+        Ok(())
+    }
+
+    /// Saves a still of the screen, if the sink knows how.
+    fn screenshot(&mut self, _frame_buffer: &FrameBuffer) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called at the start and whenever the run loop's state changes.
+    fn status(&mut self, _status: &Status) -> Result<()> {
+        Ok(())
+    }
+
+    /// Shows or hides the status bar, if the sink has one.
+    fn toggle_status_bar(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// What a frontend can ask of the run loop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    /// the CHIP-8 key now held down, if any
+    Key(Option<u8>),
+    /// the key now held down on CHIP-8X's second keypad, if any
+    Key2(Option<u8>),
+    Quit,
+    /// toggle pause
+    Pause,
+    /// run exactly one frame and pause
+    Step,
+    /// run faster while true
+    Turbo(bool),
+    /// toggle slow motion
+    SlowMotion,
+    Screenshot,
+    /// show or hide the status bar
+    StatusBar,
+}
+
+/// Something that supplies keypresses and hotkeys.
+pub trait InputSource {
+    /// Everything that happened since the last poll. Must not block.
+    fn poll(&mut self) -> Result<Vec<Input>>;
+}
+
+/// Something that plays the sound timer's tone.
+pub trait AudioSink {
+    /// Called once per frame with whether the sound timer is running.
+    fn set_tone(&mut self, on: bool) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunOptions {
+    /// how many instructions to run each 60Hz frame
+    pub timing: Timing,
+    /// sleep between frames to run at 60Hz, otherwise run as fast as possible
+    pub realtime: bool,
+    /// stop after this many frames
+    pub max_frames: Option<u64>,
+    /// speed multiplier in turbo, 0 for as fast as possible
+    pub turbo: u32,
+}
+
+/// How fast the run loop is going relative to 60Hz.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    Normal,
+    /// this many times faster, 0 for as fast as possible
+    Turbo(u32),
+    /// this many times slower
+    Slow(u32),
+}
+
+impl Speed {
+    // how long a frame should take, none for no waiting
+    fn frame_time(self) -> Option<Duration> {
+        match self {
+            Speed::Normal => Some(FRAME),
+            Speed::Turbo(0) => None,
+            Speed::Turbo(times) => Some(FRAME / times),
+            Speed::Slow(times) => Some(FRAME * times),
+        }
+    }
+}
+
+/// What the run loop is doing, for display in a title or status bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Status {
+    pub paused: bool,
+    pub speed: Speed,
+    /// frames per second actually run, measured over the last second
+    pub fps: u32,
+    /// instructions per second actually run, measured over the last second
+    pub ips: u64,
+    /// how many instructions each frame should run
+    pub timing: Timing,
+    /// whether the sound timer is running
+    pub sound: bool,
+    /// the CHIP-8 key held down
+    pub key: Option<u8>,
+}
+
+/// Why the run loop returned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Quit,
+    FrameLimit,
+}
+
+/// The run loop shared by every frontend. Faults are returned as errors.
+pub fn run(
+    system: &mut dyn Machine,
+    video: &mut dyn VideoSink,
+    input: &mut dyn InputSource,
+    audio: &mut dyn AudioSink,
+    options: RunOptions,
+) -> Result<Exit> {
+    let mut status = Status {
+        paused: false,
+        speed: Speed::Normal,
+        fps: 0,
+        ips: 0,
+        timing: options.timing,
+        sound: false,
+        key: system.key(),
+    };
+    let (mut turbo, mut slow) = (false, false);
+    let mut frames = 0;
+    let mut next_frame = Instant::now();
+    // when the speed was last measured, and the frame and instruction counts then
+    let mut measured = (Instant::now(), frames, system.instructions());
+    video.status(&status)?;
+
+    loop {
+        if Some(frames) == options.max_frames {
+            return Ok(Exit::FrameLimit);
+        }
+
+        let mut step = false;
+        let last = status;
+        for event in input.poll()? {
+            match event {
+                Input::Key(key) => system.set_key(key),
+                Input::Key2(key) => system.set_key2(key),
+                Input::Quit => return Ok(Exit::Quit),
+                Input::Pause => status.paused = !status.paused,
+                Input::Step => {
+                    status.paused = true;
+                    step = true;
+                }
+                Input::Turbo(on) => turbo = on,
+                Input::SlowMotion => slow = !slow,
+                Input::Screenshot => video.screenshot(system.frame_buffer())?,
+                Input::StatusBar => video.toggle_status_bar()?,
+            }
+        }
+        // turbo wins over slow motion while it is held
+        status.speed = match (turbo, slow) {
+            (true, _) => Speed::Turbo(options.turbo),
+            (false, true) => Speed::Slow(SLOW_MOTION),
+            (false, false) => Speed::Normal,
+        };
+
+        if !status.paused || step {
+            let changed = system.step_frame(options.timing)?;
+            if let Some(colours) = system.colours() {
+                video.colours(colours)?;
+            }
+            // only draw when there was a draw call, once per frame however many
+            if changed {
+                video.present(system.frame_buffer())?;
+            }
+            audio.set_tone(system.sound_active())?;
+            video.end_frame(system.frame_buffer())?;
+            frames += 1;
+        }
+
+        let elapsed = measured.0.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let per_second = |count: u64| (count as f64 / elapsed.as_secs_f64()).round();
+            status.fps = per_second(frames - measured.1) as u32;
+            status.ips = per_second(system.instructions() - measured.2) as u64;
+            measured = (Instant::now(), frames, system.instructions());
+        }
+        status.sound = system.sound_active();
+        status.key = system.key();
+        if status != last {
+            video.status(&status)?;
+        }
+
+        // keep polling input at 60Hz while paused
+        let frame_time = match status.paused {
+            true => Some(FRAME),
+            false => status.speed.frame_time(),
+        };
+        if let (true, Some(frame_time)) = (options.realtime, frame_time) {
+            next_frame += frame_time;
+            match next_frame.checked_duration_since(Instant::now()) {
+                Some(wait) => thread::sleep(wait),
+                // running behind, don't try to catch up
+                None => next_frame = Instant::now(),
+            }
+        } else {
+            next_frame = Instant::now();
+        }
+    }
+}
+
+/// A frontend with no screen, speaker or keyboard, for tests and batch runs.
+/// Inputs can be queued up to be delivered on a given frame.
+#[derive(Debug, Default)]
+pub struct Headless {
+    frame: u64,
+    script: Vec<(u64, Input)>,
+    /// how many times the screen changed
+    pub presented: u64,
+    /// the last status reported
+    pub status: Option<Status>,
+}
+
+impl Headless {
+    pub fn new() -> Headless {
+        Headless::default()
+    }
+
+    /// Delivers `input` at the start of `frame`.
+    pub fn at(mut self, frame: u64, input: Input) -> Headless {
+        self.script.push((frame, input));
+        self
+    }
+}
+
+impl VideoSink for Headless {
+    fn present(&mut self, _frame_buffer: &FrameBuffer) -> Result<()> {
+        self.presented += 1;
+        Ok(())
+    }
+
+    fn status(&mut self, status: &Status) -> Result<()> {
+        self.status = Some(*status);
+        Ok(())
+    }
+}
+
+impl InputSource for Headless {
+    fn poll(&mut self) -> Result<Vec<Input>> {
+        let frame = self.frame;
+        self.frame += 1;
+        let (now, later) = self.script.drain(..).partition(|(at, _)| *at == frame);
+        self.script = later;
+        Ok(now.into_iter().map(|(_, input)| input).collect())
+    }
+}
+
+impl AudioSink for Headless {
+    fn set_tone(&mut self, _on: bool) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Shows the screen on several sinks at once, e.g. the terminal and a recording.
+#[derive(Default)]
+pub struct Fanout<'a> {
+    sinks: Vec<&'a mut dyn VideoSink>,
+}
+
+impl<'a> Fanout<'a> {
+    pub fn new() -> Fanout<'a> {
+        Fanout::default()
+    }
+
+    pub fn with(mut self, sink: &'a mut dyn VideoSink) -> Fanout<'a> {
+        self.sinks.push(sink);
+        self
+    }
+}
+
+impl VideoSink for Fanout<'_> {
+    fn present(&mut self, frame_buffer: &FrameBuffer) -> Result<()> {
+        self.sinks
+            .iter_mut()
+            .try_for_each(|s| s.present(frame_buffer))
+    }
+
+    fn end_frame(&mut self, frame_buffer: &FrameBuffer) -> Result<()> {
+        self.sinks
+            .iter_mut()
+            .try_for_each(|s| s.end_frame(frame_buffer))
+    }
+
+    fn colours(&mut self, colours: &Colours) -> Result<()> {
+        self.sinks.iter_mut().try_for_each(|s| s.colours(colours))
+    }
+
+    fn screenshot(&mut self, frame_buffer: &FrameBuffer) -> Result<()> {
+        self.sinks
+            .iter_mut()
+            .try_for_each(|s| s.screenshot(frame_buffer))
+    }
+
+    fn status(&mut self, status: &Status) -> Result<()> {
+        self.sinks.iter_mut().try_for_each(|s| s.status(status))
+    }
+
+    fn toggle_status_bar(&mut self) -> Result<()> {
+        self.sinks
+            .iter_mut()
+            .try_for_each(|s| s.toggle_status_bar())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::Rom;
+
+    // LD V0, K / DRW V0, V0, 1 / JP 0x200
+    const ROM: [u8; 6] = [0xF0, 0x0A, 0xD0, 0x01, 0x12, 0x00];
+
+    fn options(max_frames: u64) -> RunOptions {
+        RunOptions {
+            timing: Timing::Ipf(10),
+            realtime: false,
+            max_frames: Some(max_frames),
+            turbo: 0,
+        }
+    }
+
+    #[test]
+    fn frame_limit() {
+        let mut system = System::init(&Rom::new(ROM.to_vec())).unwrap();
+        let mut headless = Headless::new();
+        let mut audio = Headless::new();
+        let mut input = Headless::new().at(2, Input::Key(Some(0x1)));
+
+        let exit = run(
+            &mut system,
+            &mut headless,
+            &mut input,
+            &mut audio,
+            options(5),
+        )
+        .unwrap();
+
+        assert_eq!(Exit::FrameLimit, exit);
+        assert!(headless.presented > 0, "should present after the key");
+        assert_eq!(Some(0x1), system.key());
+    }
+
+    #[test]
+    fn fanout_toggles_status_bar() {
+        // counts the toggles it gets
+        struct StatusBar(u32);
+
+        impl VideoSink for StatusBar {
+            fn present(&mut self, _frame_buffer: &FrameBuffer) -> Result<()> {
+                Ok(())
+            }
+
+            fn toggle_status_bar(&mut self) -> Result<()> {
+                self.0 += 1;
+                Ok(())
+            }
+        }
+
+        let mut system = System::init(&Rom::new(ROM.to_vec())).unwrap();
+        let mut status_bar = StatusBar(0);
+        let mut audio = Headless::new();
+        let mut input = Headless::new()
+            .at(1, Input::StatusBar)
+            .at(3, Input::StatusBar);
+
+        run(
+            &mut system,
+            &mut Fanout::new().with(&mut status_bar),
+            &mut input,
+            &mut audio,
+            options(5),
+        )
+        .unwrap();
+
+        assert_eq!(2, status_bar.0);
+    }
+
+    #[test]
+    fn quit_and_pause() {
+        let mut system = System::init(&Rom::new(ROM.to_vec())).unwrap();
+        let mut video = Headless::new();
+        let mut audio = Headless::new();
+        let mut input = Headless::new()
+            .at(0, Input::Key(Some(0x1)))
+            .at(0, Input::Pause)
+            .at(3, Input::Quit);
+
+        let exit = run(
+            &mut system,
+            &mut video,
+            &mut input,
+            &mut audio,
+            options(100),
+        )
+        .unwrap();
+
+        assert_eq!(Exit::Quit, exit);
+        assert_eq!(0, video.presented, "should not run while paused");
+    }
+
+    #[test]
+    fn step_and_speed() {
+        // ADD V0, 1 / JP 0x200
+        let mut system = System::init(&Rom::new(vec![0x70, 0x01, 0x12, 0x00])).unwrap();
+        let mut video = Headless::new();
+        let mut audio = Headless::new();
+        let mut input = Headless::new()
+            .at(0, Input::Pause)
+            .at(2, Input::Step)
+            .at(3, Input::Turbo(true))
+            .at(4, Input::SlowMotion)
+            .at(5, Input::Quit);
+
+        run(
+            &mut system,
+            &mut video,
+            &mut input,
+            &mut audio,
+            options(100),
+        )
+        .unwrap();
+
+        let status = video.status.unwrap();
+        assert_eq!(5, system.v()[0], "should run exactly one frame");
+        assert!(status.paused);
+        assert_eq!(
+            Speed::Turbo(0),
+            status.speed,
+            "turbo should win over slow motion"
+        );
+        assert_eq!(Timing::Ipf(10), status.timing);
+    }
+
+    #[test]
+    fn faults_are_errors() {
+        let mut system = System::init(&Rom::new(vec![0x00, 0x00])).unwrap();
+        let mut headless = Headless::new();
+        let mut input = Headless::new();
+        let mut audio = Headless::new();
+
+        let result = run(
+            &mut system,
+            &mut headless,
+            &mut input,
+            &mut audio,
+            options(1),
+        );
+
+        assert!(result.is_err());
+    }
+}
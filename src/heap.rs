@@ -1,7 +1,12 @@
-use std::fs;
+use crate::op_code::OpCode;
+use crate::rom::Rom;
+use anyhow::Result;
+
+pub const MEMORY_SIZE: usize = 4096;
 
 // 0x050 - 0x09F
-const FONT_START: usize = 0x050;
+pub const FONT_START: usize = 0x050;
+pub const FONT_END: usize = FONT_START + FONT.len();
 const FONT: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
@@ -24,37 +29,75 @@ const FONT: [u8; 80] = [
 pub const ROM_START: u16 = 0x200;
 
 pub struct Heap {
-    mem: [u8; 4096],
+    mem: [u8; MEMORY_SIZE],
+    // the instruction decoded at each address, dropped when its bytes are written
+    decoded: Vec<Option<OpCode>>,
+    // how many times decoded instructions were written over or forgotten
+    code_writes: u64,
 }
 
 impl Heap {
     pub fn new() -> Heap {
-        Heap { mem: [0; 4096] }
+        Heap {
+            mem: [0; MEMORY_SIZE],
+            decoded: vec![None; MEMORY_SIZE],
+            code_writes: 0,
+        }
     }
 
-    // used for testing... for now hopefully
-    #[allow(dead_code)]
     pub fn set_byte(&mut self, addr: usize, value: u8) {
         self.mem[addr] = value;
+        // the instruction at the byte before ends with this one
+        let before = (addr + MEMORY_SIZE - 1) % MEMORY_SIZE;
+        if self.decoded[addr].is_some() || self.decoded[before].is_some() {
+            self.decoded[addr] = None;
+            self.decoded[before] = None;
+            self.code_writes += 1;
+        }
     }
 
-    pub fn fetch_byte(&mut self, addr: usize) -> u8 {
+    pub fn fetch_byte(&self, addr: usize) -> u8 {
         self.mem[addr]
     }
 
-    pub fn load_font(&mut self) {
-        for (i, byte) in FONT.into_iter().enumerate() {
-            self.mem[FONT_START + i] = byte;
+    pub fn bytes(&self) -> &[u8] {
+        &self.mem
+    }
+
+    /// The instruction last decoded at `addr`, if its bytes haven't been written since.
+    pub fn decoded(&self, addr: usize) -> Option<OpCode> {
+        self.decoded.get(addr).copied().flatten()
+    }
+
+    pub fn set_decoded(&mut self, addr: usize, op_code: OpCode) {
+        if let Some(slot) = self.decoded.get_mut(addr) {
+            *slot = Some(op_code);
         }
     }
 
-    pub fn load_rom(&mut self, rom_path: &String) {
-        for (i, b) in fs::read(rom_path).unwrap().into_iter().enumerate() {
-            self.mem[ROM_START as usize + i] = b;
+    /// Forgets every decoded instruction, for when they would decode differently.
+    pub fn clear_decoded(&mut self) {
+        self.decoded.fill(None);
+        self.code_writes += 1;
+    }
+
+    /// Counts the writes that changed decoded instructions, so anything built from them
+    /// can tell when it is out of date.
+    pub fn code_writes(&self) -> u64 {
+        self.code_writes
+    }
+
+    pub fn load_font(&mut self) {
+        for (i, byte) in FONT.into_iter().enumerate() {
+            self.mem[FONT_START + i] = byte;
         }
     }
 
-    pub fn fetch_op(&self, address: usize) -> u16 {
-        (self.mem[address] as u16) << 8 | (self.mem[address + 1] as u16)
+    pub fn load_rom(&mut self, rom: &Rom) -> Result<()> {
+        rom.validate()?;
+        let start = rom.load_address as usize;
+        self.mem[start..start + rom.data.len()].copy_from_slice(&rom.data);
+        self.clear_decoded();
+        Ok(())
     }
 }
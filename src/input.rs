@@ -0,0 +1,220 @@
+use crate::keymap::{Action, Hotkeys, Keymap};
+use anyhow::Result;
+use chip8::frontend::{Input, InputSource};
+use crossterm::{
+    event::{
+        self, Event::Key, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    terminal, ExecutableCommand,
+};
+use std::{io::stdout, time::Duration};
+
+/// Keyboard input from the terminal, through the keymap and hotkeys.
+pub struct TerminalInput {
+    keymap: Keymap,
+    hotkeys: Hotkeys,
+    // whether the terminal reports key releases, otherwise turbo toggles
+    releases: bool,
+    turbo: bool,
+    // the keypad key last pressed, which only its own release lets go of
+    held: Option<u8>,
+    // the same for the second keypad
+    held2: Option<u8>,
+}
+
+impl TerminalInput {
+    /// Asks the terminal to report key releases where it can, so needs raw mode.
+    pub fn new(keymap: Keymap, hotkeys: Hotkeys) -> Result<TerminalInput> {
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            stdout().execute(PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
+            ))?;
+        }
+        Ok(TerminalInput {
+            keymap,
+            hotkeys,
+            releases,
+            turbo: false,
+            held: None,
+            held2: None,
+        })
+    }
+}
+
+impl Drop for TerminalInput {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = stdout().execute(PopKeyboardEnhancementFlags);
+        }
+    }
+}
+
+impl InputSource for TerminalInput {
+    fn poll(&mut self) -> Result<Vec<Input>> {
+        let mut inputs = Vec::new();
+        while event::poll(Duration::ZERO)? {
+            if let Key(key) = event::read()? {
+                inputs.extend(self.let_go_of_other(&key));
+                inputs.extend(self.translate(key));
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+impl TerminalInput {
+    // without releases, pressing a key lets go of the other keypad's key as well
+    fn let_go_of_other(&mut self, key: &KeyEvent) -> Option<Input> {
+        if self.releases
+            || key.kind == KeyEventKind::Release
+            || self.hotkeys.action(key.code).is_some()
+        {
+            return None;
+        }
+        if self.keymap.key2(key.code).is_some() {
+            self.held.take().map(|_| Input::Key(None))
+        } else {
+            self.held2.take().map(|_| Input::Key2(None))
+        }
+    }
+
+    // what a key event means, if anything
+    fn translate(&mut self, key: KeyEvent) -> Option<Input> {
+        let input = match (key.kind, self.hotkeys.action(key.code)) {
+            (KeyEventKind::Release, Some(Action::Turbo)) => {
+                self.turbo = false;
+                Input::Turbo(false)
+            }
+            (KeyEventKind::Release, _) => {
+                // another key may have been pressed since, which is still held. Shift may
+                // come up first, so a key lets go of either keypad's key in its place
+                let keys = [self.keymap.key(key.code), self.keymap.key2(key.code)];
+                if self.held.is_some() && keys.contains(&self.held) {
+                    self.held = None;
+                    Input::Key(None)
+                } else if self.held2.is_some() && keys.contains(&self.held2) {
+                    self.held2 = None;
+                    Input::Key2(None)
+                } else {
+                    return None;
+                }
+            }
+            // holding step keeps stepping, other hotkeys fire once
+            (KeyEventKind::Repeat, Some(Action::Step)) => Input::Step,
+            (KeyEventKind::Repeat, Some(_)) => return None,
+            (_, Some(Action::Quit)) => Input::Quit,
+            (_, Some(Action::Pause)) => Input::Pause,
+            (_, Some(Action::Step)) => Input::Step,
+            (_, Some(Action::Turbo)) => {
+                self.turbo = self.releases || !self.turbo;
+                Input::Turbo(self.turbo)
+            }
+            (_, Some(Action::SlowMotion)) => Input::SlowMotion,
+            (_, Some(Action::Screenshot)) => Input::Screenshot,
+            (_, Some(Action::StatusBar)) => Input::StatusBar,
+            (_, None) if self.keymap.key2(key.code).is_some() => {
+                self.held2 = self.keymap.key2(key.code);
+                Input::Key2(self.held2)
+            }
+            // without releases, any other key is how a keypad key gets let go
+            (_, None) if self.releases && self.keymap.key(key.code).is_none() => return None,
+            (_, None) => {
+                self.held = self.keymap.key(key.code);
+                Input::Key(self.held)
+            }
+        };
+        Some(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    fn input() -> TerminalInput {
+        TerminalInput {
+            keymap: Keymap::default(),
+            hotkeys: Hotkeys::default(),
+            releases: true,
+            turbo: false,
+            held: None,
+            held2: None,
+        }
+    }
+
+    fn event(c: char, kind: KeyEventKind) -> KeyEvent {
+        KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::NONE, kind)
+    }
+
+    #[test]
+    fn release_only_held_key() {
+        let mut input = input();
+        let (press, release) = (KeyEventKind::Press, KeyEventKind::Release);
+
+        assert_eq!(
+            Some(Input::Key(Some(0x4))),
+            input.translate(event('q', press))
+        );
+        assert_eq!(
+            Some(Input::Key(Some(0x5))),
+            input.translate(event('w', press))
+        );
+        assert_eq!(
+            None,
+            input.translate(event('q', release)),
+            "w is still held"
+        );
+        assert_eq!(None, input.translate(event('i', release)), "a hotkey");
+        assert_eq!(
+            None,
+            input.translate(event('j', press)),
+            "not on the keypad"
+        );
+        assert_eq!(None, input.translate(event('j', release)));
+        assert_eq!(Some(Input::Key(None)), input.translate(event('w', release)));
+    }
+
+    #[test]
+    fn second_keypad() {
+        let mut input = input();
+        let (press, release) = (KeyEventKind::Press, KeyEventKind::Release);
+
+        input.translate(event('q', press));
+        assert_eq!(
+            Some(Input::Key2(Some(0x5))),
+            input.translate(event('W', press))
+        );
+        assert_eq!(Some(Input::Key(None)), input.translate(event('q', release)));
+        assert_eq!(
+            Some(Input::Key2(None)),
+            input.translate(event('w', release)),
+            "Shift came up first"
+        );
+    }
+
+    #[test]
+    fn without_releases() {
+        let mut input = input();
+        input.releases = false;
+
+        input.translate(event('q', KeyEventKind::Press));
+
+        assert_eq!(
+            Some(Input::Key(None)),
+            input.translate(event('j', KeyEventKind::Press)),
+            "another key should let go"
+        );
+
+        let q = event('q', KeyEventKind::Press);
+        input.translate(event('Q', KeyEventKind::Press));
+        assert_eq!(
+            Some(Input::Key2(None)),
+            input.let_go_of_other(&q),
+            "so should one on the other keypad"
+        );
+        assert_eq!(None, input.let_go_of_other(&q));
+    }
+}
@@ -0,0 +1,348 @@
+use anyhow::{anyhow, bail, Result};
+use crossterm::event::KeyCode;
+use std::collections::HashMap;
+
+// The CHIP-8 keypad as laid out on the COSMAC VIP, row by row:
+// 1 2 3 C
+// 4 5 6 D
+// 7 8 9 E
+// A 0 B F
+const KEYPAD: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF, //
+];
+
+// host keys in the same physical positions as KEYPAD
+const QWERTY: &str = "1234qwerasdfzxcv";
+const AZERTY: &str = "&é\"'azerqsdfwxcv";
+const DVORAK: &str = "1234',.paoeu;qjk";
+
+// what keys on US keyboards' number row and punctuation give with Shift
+const UNSHIFTED: &str = "1234567890',.;/[]-=`\\";
+const SHIFTED: &str = "!@#$%^&*()\"<>:?{}_+~|";
+
+/// Maps host keys onto the 16 CHIP-8 keys, and onto the second keypad CHIP-8X has,
+/// which by default is the same keys with Shift held.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    keys: HashMap<KeyCode, u8>,
+    keys2: HashMap<KeyCode, u8>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::qwerty()
+    }
+}
+
+impl Keymap {
+    pub fn qwerty() -> Keymap {
+        Keymap::from_layout(QWERTY).unwrap()
+    }
+
+    pub fn azerty() -> Keymap {
+        let mut keymap = Keymap::from_layout(AZERTY).unwrap();
+        // Shift gives the digits on AZERTY's number row
+        for (c, key) in "1234".chars().zip(KEYPAD) {
+            keymap.set2(KeyCode::Char(c), key);
+        }
+        keymap
+    }
+
+    pub fn dvorak() -> Keymap {
+        Keymap::from_layout(DVORAK).unwrap()
+    }
+
+    /// The original hex layout where '0'-'f' map straight to their key.
+    pub fn hex() -> Keymap {
+        let layout: String = KEYPAD
+            .iter()
+            .map(|k| char::from_digit((*k).into(), 16).unwrap())
+            .collect();
+        Keymap::from_layout(&layout).unwrap()
+    }
+
+    pub fn preset(name: &str) -> Option<Keymap> {
+        match name.to_lowercase().as_str() {
+            "qwerty" => Some(Keymap::qwerty()),
+            "azerty" => Some(Keymap::azerty()),
+            "dvorak" => Some(Keymap::dvorak()),
+            "hex" => Some(Keymap::hex()),
+            _ => None,
+        }
+    }
+
+    /// Builds a keymap from 16 host keys given in keypad order (1 2 3 C, 4 5 6 D, ...).
+    /// Whitespace is ignored so layouts can be written as four rows.
+    pub fn from_layout(layout: &str) -> Result<Keymap> {
+        let host: Vec<char> = layout.chars().filter(|c| !c.is_whitespace()).collect();
+        if host.len() != KEYPAD.len() {
+            bail!(
+                "keypad layout needs {} keys but got {}: {:?}",
+                KEYPAD.len(),
+                host.len(),
+                layout
+            );
+        }
+        let mut keymap = Keymap {
+            keys: HashMap::new(),
+            keys2: HashMap::new(),
+        };
+        for (c, key) in host.iter().zip(KEYPAD) {
+            keymap.set(KeyCode::Char(*c), key);
+        }
+        for (c, key) in host.iter().zip(KEYPAD) {
+            if let Some(shifted) = shifted(*c).filter(|s| !host.contains(s)) {
+                keymap.set2(KeyCode::Char(shifted), key);
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Binds a host key to a CHIP-8 key, replacing any previous binding of that key.
+    pub fn set(&mut self, host: KeyCode, key: u8) {
+        self.keys.retain(|_, k| *k != key);
+        self.keys2.remove(&host);
+        self.keys.insert(host, key);
+    }
+
+    /// Binds a host key to a CHIP-8 key, keeping any other host keys bound to it.
+    pub fn add(&mut self, host: KeyCode, key: u8) {
+        self.keys2.remove(&host);
+        self.keys.insert(host, key);
+    }
+
+    pub fn key(&self, host: KeyCode) -> Option<u8> {
+        self.keys.get(&host).copied()
+    }
+
+    /// Binds a host key to a key on the second keypad, replacing any previous binding of
+    /// that key.
+    pub fn set2(&mut self, host: KeyCode, key: u8) {
+        self.keys2.retain(|_, k| *k != key);
+        self.keys.remove(&host);
+        self.keys2.insert(host, key);
+    }
+
+    pub fn key2(&self, host: KeyCode) -> Option<u8> {
+        self.keys2.get(&host).copied()
+    }
+}
+
+// `c` typed with Shift, if that is a different key
+fn shifted(c: char) -> Option<char> {
+    if c.is_lowercase() {
+        let mut upper = c.to_uppercase();
+        return match (upper.next(), upper.next()) {
+            (Some(upper), None) => Some(upper),
+            _ => None,
+        };
+    }
+    UNSHIFTED
+        .chars()
+        .zip(SHIFTED.chars())
+        .find_map(|(plain, shifted)| (plain == c).then_some(shifted))
+}
+
+/// Emulator controls, kept apart from the keypad so they never collide with a game key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    Pause,
+    /// advance one frame while paused
+    Step,
+    /// run faster while held
+    Turbo,
+    SlowMotion,
+    Screenshot,
+    /// show or hide the status bar
+    StatusBar,
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        match name.to_lowercase().as_str() {
+            "quit" => Some(Action::Quit),
+            "pause" => Some(Action::Pause),
+            "step" => Some(Action::Step),
+            "turbo" => Some(Action::Turbo),
+            "slowmo" | "slow-motion" => Some(Action::SlowMotion),
+            "screenshot" => Some(Action::Screenshot),
+            "status" | "status-bar" => Some(Action::StatusBar),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotkeys {
+    actions: HashMap<KeyCode, Action>,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        let mut hotkeys = Hotkeys {
+            actions: HashMap::new(),
+        };
+        hotkeys.set(KeyCode::Esc, Action::Quit);
+        // not 'p', which is a keypad key on Dvorak
+        hotkeys.set(KeyCode::Char(' '), Action::Pause);
+        hotkeys.set(KeyCode::Char('n'), Action::Step);
+        hotkeys.set(KeyCode::Tab, Action::Turbo);
+        hotkeys.set(KeyCode::Char('m'), Action::SlowMotion);
+        hotkeys.set(KeyCode::F(12), Action::Screenshot);
+        hotkeys.set(KeyCode::Char('i'), Action::StatusBar);
+        hotkeys
+    }
+}
+
+impl Hotkeys {
+    /// Binds a host key to an action, replacing any previous binding of that action.
+    pub fn set(&mut self, host: KeyCode, action: Action) {
+        self.actions.retain(|_, a| *a != action);
+        self.actions.insert(host, action);
+    }
+
+    pub fn action(&self, host: KeyCode) -> Option<Action> {
+        self.actions.get(&host).copied()
+    }
+
+    /// The first hotkey that is also bound to a key on either keypad, which would hide it.
+    pub fn overlap(&self, keymap: &Keymap) -> Option<(KeyCode, Action, u8)> {
+        self.actions.iter().find_map(|(host, action)| {
+            let key = keymap.key(*host).or(keymap.key2(*host))?;
+            Some((*host, *action, key))
+        })
+    }
+}
+
+/// Parses a host key name such as "q", "esc", "space" or "f1".
+pub fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    match name.to_lowercase().as_str() {
+        "esc" | "escape" => Ok(KeyCode::Esc),
+        "space" => Ok(KeyCode::Char(' ')),
+        "enter" | "return" => Ok(KeyCode::Enter),
+        "tab" => Ok(KeyCode::Tab),
+        "backspace" => Ok(KeyCode::Backspace),
+        "up" => Ok(KeyCode::Up),
+        "down" => Ok(KeyCode::Down),
+        "left" => Ok(KeyCode::Left),
+        "right" => Ok(KeyCode::Right),
+        other => other
+            .strip_prefix('f')
+            .and_then(|n| n.parse().ok())
+            .map(KeyCode::F)
+            .ok_or_else(|| anyhow!("unknown key name {:?}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qwerty() {
+        let keymap = Keymap::default();
+
+        assert_eq!(Some(0x1), keymap.key(KeyCode::Char('1')));
+        assert_eq!(Some(0xC), keymap.key(KeyCode::Char('4')));
+        assert_eq!(Some(0x4), keymap.key(KeyCode::Char('q')));
+        assert_eq!(Some(0x0), keymap.key(KeyCode::Char('x')));
+        assert_eq!(Some(0xF), keymap.key(KeyCode::Char('v')));
+        assert_eq!(None, keymap.key(KeyCode::Char('p')));
+    }
+
+    #[test]
+    fn from_layout() {
+        let keymap = Keymap::from_layout("1234 qwer asdf zxcv").unwrap();
+        assert_eq!(Keymap::qwerty(), keymap);
+
+        assert!(
+            Keymap::from_layout("1234").is_err(),
+            "should need all 16 keys"
+        );
+    }
+
+    #[test]
+    fn set_rebinds() {
+        let mut keymap = Keymap::qwerty();
+
+        keymap.set(KeyCode::Up, 0x5);
+
+        assert_eq!(Some(0x5), keymap.key(KeyCode::Up));
+        assert_eq!(
+            None,
+            keymap.key(KeyCode::Char('w')),
+            "should drop old binding"
+        );
+    }
+
+    #[test]
+    fn add_keeps_bindings() {
+        let mut keymap = Keymap::qwerty();
+
+        keymap.add(KeyCode::Up, 0x5);
+
+        assert_eq!(Some(0x5), keymap.key(KeyCode::Up));
+        assert_eq!(Some(0x5), keymap.key(KeyCode::Char('w')));
+    }
+
+    #[test]
+    fn second_keypad() {
+        let keymap = Keymap::qwerty();
+        assert_eq!(Some(0x1), keymap.key2(KeyCode::Char('!')));
+        assert_eq!(Some(0x4), keymap.key2(KeyCode::Char('Q')));
+        assert_eq!(Some(0xF), keymap.key2(KeyCode::Char('V')));
+        assert_eq!(None, keymap.key(KeyCode::Char('Q')));
+        assert_eq!(None, keymap.key2(KeyCode::Char('q')));
+
+        assert_eq!(Some(0xA), Keymap::hex().key2(KeyCode::Char('A')));
+        assert_eq!(Some(0x0), Keymap::hex().key2(KeyCode::Char(')')));
+        assert_eq!(Some(0x2), Keymap::azerty().key2(KeyCode::Char('2')));
+        assert_eq!(None, Keymap::azerty().key2(KeyCode::Char('É')));
+        assert_eq!(Some(0x5), Keymap::dvorak().key2(KeyCode::Char('<')));
+
+        let mut keymap = Keymap::qwerty();
+        keymap.set(KeyCode::Char('Q'), 0x4);
+        assert_eq!(None, keymap.key2(KeyCode::Char('Q')), "should move it over");
+    }
+
+    #[test]
+    fn hotkeys_do_not_overlap_keypad() {
+        let hotkeys = Hotkeys::default();
+        assert_eq!(Some(Action::Quit), hotkeys.action(KeyCode::Esc));
+        assert_eq!(None, hotkeys.action(KeyCode::Char('q')));
+
+        for name in ["qwerty", "azerty", "dvorak", "hex"] {
+            let keymap = Keymap::preset(name).unwrap();
+            assert_eq!(None, hotkeys.overlap(&keymap), "{} overlaps", name);
+        }
+        let mut keymap = Keymap::qwerty();
+        keymap.set(KeyCode::Esc, 0x1);
+        assert_eq!(
+            Some((KeyCode::Esc, Action::Quit, 0x1)),
+            hotkeys.overlap(&keymap)
+        );
+        let mut keymap = Keymap::qwerty();
+        keymap.set2(KeyCode::Tab, 0x2);
+        assert_eq!(
+            Some((KeyCode::Tab, Action::Turbo, 0x2)),
+            hotkeys.overlap(&keymap)
+        );
+    }
+
+    #[test]
+    fn parse_key_names() {
+        assert_eq!(KeyCode::Char('q'), parse_key("q").unwrap());
+        assert_eq!(KeyCode::Esc, parse_key("Esc").unwrap());
+        assert_eq!(KeyCode::Char(' '), parse_key("space").unwrap());
+        assert_eq!(KeyCode::F(5), parse_key("f5").unwrap());
+        assert!(parse_key("nope").is_err());
+    }
+}
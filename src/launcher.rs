@@ -0,0 +1,394 @@
+use crate::cli::RunArgs;
+use crate::config::{self, Config};
+use crate::display::TerminalGuard;
+use crate::screen::{self, RenderMode, Screen};
+use anyhow::{Context, Result};
+use chip8::{
+    database::Database, frontend::Machine, phosphor, rom::Format, timing::Timing, Rom, Theme,
+};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+use std::{
+    collections::HashSet,
+    fs,
+    io::{stdout, Stdout},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+// frames run before the thumbnail is shown, to get past start up screens
+const WARM_UP: u32 = 120;
+
+/// A ROM found in the launcher's directory.
+struct Entry {
+    path: PathBuf,
+    hash: String,
+    /// the database title, or the file name
+    title: String,
+    /// short platform names from the database, e.g. "SCHIP"
+    tags: Vec<&'static str>,
+}
+
+// a ROM running in the background for its thumbnail
+struct Thumbnail {
+    system: Box<dyn Machine>,
+    timing: Timing,
+    theme: Theme,
+    faulted: bool,
+}
+
+/// Lists the ROMs in a directory to pick one to play. Remembers the selection, the
+/// filter and favourites between games.
+pub struct Launcher {
+    entries: Vec<Entry>,
+    favourites: HashSet<String>,
+    favourites_path: Option<PathBuf>,
+    filter: String,
+    filtering: bool,
+    state: ListState,
+    thumbnail: Option<Thumbnail>,
+    // shown at the bottom until the next key, e.g. why the last game stopped
+    message: Option<String>,
+}
+
+impl Launcher {
+    pub fn new(dir: &Path) -> Result<Launcher> {
+        let mut entries = Vec::new();
+        let files =
+            fs::read_dir(dir).with_context(|| format!("could not read {}", dir.display()))?;
+        for path in files.filter_map(|file| Some(file.ok()?.path())) {
+            if Format::from_path(&path).is_none() {
+                continue;
+            }
+            // not everything with a ROM extension is a ROM, e.g. a README.txt
+            let Ok(rom) = Rom::read(&path) else {
+                continue;
+            };
+            let hash = config::rom_hash(&rom.data);
+            let entry = Database::embedded().lookup(&hash);
+            let title = match entry {
+                Some(entry) => entry.program.title.clone(),
+                None => path.file_stem().unwrap().to_string_lossy().into_owned(),
+            };
+            let mut tags: Vec<_> = entry
+                .iter()
+                .flat_map(|entry| &entry.rom.platforms)
+                .filter_map(|platform| tag(platform))
+                .collect();
+            tags.dedup();
+            entries.push(Entry {
+                path,
+                hash,
+                title,
+                tags,
+            });
+        }
+
+        let favourites_path = config::default_path().map(|path| path.with_file_name("favourites"));
+        let favourites = match &favourites_path {
+            Some(path) => fs::read_to_string(path)
+                .map(|text| text.lines().map(str::to_string).collect())
+                .unwrap_or_default(),
+            None => HashSet::new(),
+        };
+
+        let mut launcher = Launcher {
+            entries,
+            favourites,
+            favourites_path,
+            filter: String::new(),
+            filtering: false,
+            state: ListState::default(),
+            thumbnail: None,
+            message: None,
+        };
+        launcher.sort();
+        launcher.state.select(Some(0));
+        Ok(launcher)
+    }
+
+    /// Shows why the last game stopped.
+    pub fn message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    /// Shows the list until a ROM is picked, or returns `None` to quit.
+    /// Thumbnails run with `args` on top of the config, like the game would.
+    pub fn pick(&mut self, config: &Config, args: &RunArgs) -> Result<Option<PathBuf>> {
+        let _guard = TerminalGuard::enter()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+        self.run(&mut terminal, config, args)
+    }
+
+    fn run(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        config: &Config,
+        args: &RunArgs,
+    ) -> Result<Option<PathBuf>> {
+        let mut shown = None;
+        loop {
+            let visible = self.visible();
+            let selected = self.state.selected().and_then(|i| visible.get(i).copied());
+            if selected != shown {
+                shown = selected;
+                self.thumbnail = selected.and_then(|i| self.thumbnail(i, config, args));
+            }
+            if let Some(thumbnail) = self.thumbnail.as_mut() {
+                if !thumbnail.faulted {
+                    thumbnail.faulted = thumbnail.system.step_frame(thumbnail.timing).is_err();
+                }
+            }
+            terminal.draw(|frame| self.draw(frame, &visible))?;
+
+            if !event::poll(Duration::from_millis(1000 / 30))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.message = None;
+            if self.filtering {
+                match key.code {
+                    KeyCode::Enter => self.filtering = false,
+                    KeyCode::Esc => {
+                        self.filtering = false;
+                        self.filter.clear();
+                    }
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Char(c) => self.filter.push(c),
+                    _ => {}
+                }
+                self.state.select(Some(0));
+                continue;
+            }
+            let count = visible.len();
+            let at = self.state.selected().unwrap_or(0);
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+                KeyCode::Enter => {
+                    if let Some(i) = selected {
+                        return Ok(Some(self.entries[i].path.clone()));
+                    }
+                }
+                KeyCode::Char('/') => self.filtering = true,
+                KeyCode::Char('f') => {
+                    if let Some(i) = selected {
+                        self.toggle_favourite(i)?;
+                    }
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.state.select(Some(at.saturating_sub(1)));
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.state
+                        .select(Some((at + 1).min(count.saturating_sub(1))));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // indexes of the entries matching the filter
+    fn visible(&self) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        (0..self.entries.len())
+            .filter(|i| {
+                let entry = &self.entries[*i];
+                let name = entry.path.file_name().unwrap().to_string_lossy();
+                entry.title.to_lowercase().contains(&filter)
+                    || name.to_lowercase().contains(&filter)
+            })
+            .collect()
+    }
+
+    // favourites first, then by title
+    fn sort(&mut self) {
+        let favourites = &self.favourites;
+        self.entries.sort_by_key(|entry| {
+            (
+                !favourites.contains(&entry.hash),
+                entry.title.to_lowercase(),
+            )
+        });
+    }
+
+    fn toggle_favourite(&mut self, i: usize) -> Result<()> {
+        let hash = self.entries[i].hash.clone();
+        if !self.favourites.remove(&hash) {
+            self.favourites.insert(hash.clone());
+        }
+        self.sort();
+        // keep the same ROM selected as it moves
+        let position = self
+            .visible()
+            .iter()
+            .position(|i| self.entries[*i].hash == hash);
+        self.state.select(position);
+
+        let Some(path) = &self.favourites_path else {
+            return Ok(());
+        };
+        let mut favourites: Vec<_> = self.favourites.iter().cloned().collect();
+        favourites.sort();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, favourites.join("\n"))
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+
+    // loaded the way `play` loads it
+    fn thumbnail(&self, i: usize, config: &Config, args: &RunArgs) -> Option<Thumbnail> {
+        let entry = &self.entries[i];
+        let rom = Rom::read(&entry.path).ok()?;
+        let settings = config.settings(&entry.hash, args.overrides()).ok()?;
+        let rom = args.emulation.place(rom, settings.load_address);
+        let mut system = crate::load(&rom, &settings, &args.emulation).ok()?;
+        let faulted = (0..WARM_UP).any(|_| system.step_frame(settings.timing).is_err());
+        Some(Thumbnail {
+            system,
+            timing: settings.timing,
+            theme: settings.theme,
+            faulted,
+        })
+    }
+
+    fn draw(&mut self, frame: &mut Frame, visible: &[usize]) {
+        let [main, bar] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.size());
+        let [list_area, preview] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|i| {
+                let entry = &self.entries[*i];
+                let star = match self.favourites.contains(&entry.hash) {
+                    true => "★ ",
+                    false => "  ",
+                };
+                let mut spans = vec![Span::raw(star), Span::raw(entry.title.clone())];
+                for tag in &entry.tags {
+                    spans.push(Span::raw(format!(" [{}]", tag)).dim());
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().title("ROMs").borders(Borders::ALL))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let selected = self.state.selected().and_then(|i| visible.get(i));
+        let title = selected.map_or(String::new(), |i| {
+            self.entries[*i].path.display().to_string()
+        });
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let inner = block.inner(preview);
+        frame.render_widget(block, preview);
+        if let Some(thumbnail) = &self.thumbnail {
+            let mode = RenderMode::HalfBlock;
+            let rows = thumbnail.system.height();
+            let (width, height) = mode.size(inner, None, rows);
+            let area = Rect::new(inner.x, inner.y, width, height);
+            let shades = phosphor::shades(thumbnail.system.frame_buffer());
+            let screen = Screen::new(&shades, mode, &thumbnail.theme).rows(rows);
+            frame.render_widget(screen, area);
+            if thumbnail.faulted {
+                let below = Rect::new(inner.x, inner.y + height, inner.width, 1);
+                frame.render_widget(Paragraph::new("stopped with a fault"), below);
+            }
+        }
+
+        let text = match (&self.message, self.filtering) {
+            (Some(message), _) => message.clone(),
+            (None, true) => format!("/{}▏", self.filter),
+            (None, false) => {
+                " enter play · / filter · f favourite · q quit".to_string()
+                    + &match self.filter.is_empty() {
+                        true => String::new(),
+                        false => format!(" · filter: {}", self.filter),
+                    }
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(text).style(Style::new().fg(screen::rgb([0x80; 3]))),
+            bar,
+        );
+    }
+}
+
+// short names for the database's platform ids
+fn tag(platform: &str) -> Option<&'static str> {
+    match platform {
+        "originalChip8" | "hybridVIP" | "modernChip8" => Some("CHIP-8"),
+        "chip8x" => Some("CHIP-8X"),
+        "chip48" | "superchip1" | "superchip" => Some("SCHIP"),
+        "megachip8" => Some("MEGACHIP"),
+        "xochip" => Some("XO-CHIP"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_roms() {
+        let launcher = Launcher::new(Path::new("roms")).unwrap();
+
+        let titles: Vec<_> = launcher.entries.iter().map(|e| e.title.as_str()).collect();
+        assert!(titles.contains(&"IBM Logo"), "{:?}", titles);
+        assert_eq!(3, titles.len());
+        let ibm = launcher
+            .entries
+            .iter()
+            .find(|e| e.title == "IBM Logo")
+            .unwrap();
+        assert_eq!(vec!["CHIP-8"], ibm.tags);
+    }
+
+    #[test]
+    fn filter() {
+        let mut launcher = Launcher::new(Path::new("roms")).unwrap();
+
+        launcher.filter = "STAR".to_string();
+
+        let visible = launcher.visible();
+        assert_eq!(1, visible.len());
+        assert_eq!("Stars", launcher.entries[visible[0]].title);
+    }
+
+    #[test]
+    fn thumbnail_placed_like_play() {
+        use crate::cli::Cli;
+        use clap::Parser;
+
+        // JP 0x300, a loop only where CHIP-8X loads it; at 0x200 it runs into zeros
+        let dir = std::env::temp_dir().join(format!("chip8-launcher-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("loop.ch8"), [0x13, 0x00]).unwrap();
+        let launcher = Launcher::new(&dir).unwrap();
+        let config = Config::default();
+
+        let chip8x = Cli::parse_from(["chip8", "--quirks", "chip8x"]).run;
+        let thumbnail = launcher.thumbnail(0, &config, &chip8x).unwrap();
+        assert!(!thumbnail.faulted, "should load at 0x300");
+
+        let modern = Cli::parse_from(["chip8"]).run;
+        let thumbnail = launcher.thumbnail(0, &config, &modern).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(thumbnail.faulted, "should load at 0x200");
+    }
+}
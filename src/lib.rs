@@ -0,0 +1,37 @@
+//! The CHIP-8 core: memory, registers, the instruction set and a screen to draw on.
+//! Frontends own the loop, feeding in keys and reading back the frame buffer.
+//!
+//! ```
+//! use chip8::{Rom, System};
+//!
+//! let rom = Rom::new(include_bytes!("../roms/ibm.ch8").to_vec());
+//! let mut system = System::init(&rom).unwrap();
+//! for _ in 0..10 {
+//!     system.run_frame(10).unwrap();
+//! }
+//! assert!(system.frame_buffer().iter().flatten().any(|px| *px));
+//! ```
+
+pub mod asm;
+mod blocks;
+pub mod cdp1802;
+pub mod chip8x;
+pub mod database;
+pub mod emulator;
+pub mod frontend;
+mod heap;
+pub mod op_code;
+pub mod phosphor;
+pub mod quirks;
+pub mod recorder;
+pub mod rom;
+pub mod theme;
+pub mod timing;
+pub mod vip;
+
+pub use emulator::{Fault, FrameBuffer, System};
+pub use heap::{MEMORY_SIZE, ROM_START};
+pub use op_code::OpCode;
+pub use quirks::Quirks;
+pub use rom::Rom;
+pub use theme::Theme;
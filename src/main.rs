@@ -1,75 +1,323 @@
+mod cli;
+mod config;
 mod display;
-mod emulator;
-mod heap;
-mod op_code;
-
-use anyhow::Result;
-use crossterm::event::{self, Event::Key, KeyCode::Char};
-use display::Display;
-use emulator::System;
-use op_code::OpCode;
-use std::env;
-
-fn main() -> Result<()> {
-    let mut display = Display::init()?;
-
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 {
-        panic!("Missing ROM path argument example: ./chip8 roms/ibm.ch8");
-    }
-    let rom_path = &args[1];
-
-    let mut system: System = System::init(rom_path);
-
-    loop {
-        let op = system.fetch();
-
-        let op_code: OpCode = op_code::decode(op);
-
-        if event::poll(std::time::Duration::from_micros(500))? {
-            if let Key(key) = event::read()? {
-                if key.kind == event::KeyEventKind::Press {
-                    match key.code {
-                        Char('q') => break,
-                        Char('0') => system.set_key(Some(0x0)),
-                        Char('1') => system.set_key(Some(0x1)),
-                        Char('2') => system.set_key(Some(0x2)),
-                        Char('3') => system.set_key(Some(0x3)),
-                        Char('4') => system.set_key(Some(0x4)),
-                        Char('5') => system.set_key(Some(0x5)),
-                        Char('6') => system.set_key(Some(0x6)),
-                        Char('7') => system.set_key(Some(0x7)),
-                        Char('8') => system.set_key(Some(0x8)),
-                        Char('9') => system.set_key(Some(0x9)),
-                        Char('a') => system.set_key(Some(0xA)),
-                        Char('b') => system.set_key(Some(0xB)),
-                        Char('c') => system.set_key(Some(0xC)),
-                        Char('d') => system.set_key(Some(0xD)),
-                        Char('e') => system.set_key(Some(0xE)),
-                        Char('f') => system.set_key(Some(0xF)),
-                        _ => system.set_key(None),
-                    }
-                } else {
-                    system.set_key(None);
-                }
-            }
+mod input;
+mod keymap;
+mod launcher;
+mod screen;
+mod screenshot;
+
+use anyhow::{anyhow, Context, Result};
+use chip8::{
+    asm,
+    database::Database,
+    frontend::{self, AudioSink, Fanout, Headless, Machine, RunOptions},
+    recorder::Recorder,
+    vip::Vip,
+    Fault, Rom, System,
+};
+use clap::Parser;
+use cli::{Cli, Command, EmulationArgs, RunArgs};
+use config::{Backend, Config, Settings};
+use display::{Bell, Display};
+use input::TerminalInput;
+use launcher::Launcher;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        None => run(cli.run),
+        Some(Command::Run(args)) => run(args),
+        Some(Command::Disasm { rom }) => disasm(&rom),
+        Some(Command::Asm { source, output }) => asm(&source, output),
+        Some(Command::Info { rom }) => info(&rom),
+        Some(Command::Test {
+            rom,
+            frames,
+            record,
+            emulation,
+        }) => test(&rom, frames, record.as_deref(), &emulation),
+        Some(Command::Bench {
+            rom,
+            cycles,
+            emulation,
+        }) => bench(&rom, cycles, &emulation),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::FAILURE
         }
-        match op_code {
-            // only draw when there is a draw call
-            OpCode::Cls | OpCode::Drw { vx: _, vy: _, n: _ } => {
-                system.execute(&op_code);
-                display.render(&system.frame_buffer);
-            }
-            OpCode::Unknown => {
-                Display::destroy()?;
-                println!("Invalid OpCode {:#06X}", op);
-                break;
+    }
+}
+
+fn load(rom: &Rom, settings: &Settings, emulation: &EmulationArgs) -> Result<Box<dyn Machine>> {
+    let (interpreter, monitor) = match &settings.backend {
+        Backend::Chip8 => {
+            let mut system = System::init(rom)?;
+            system.set_quirks(settings.quirks);
+            system.set_blocks(emulation.blocks);
+            if let Some(seed) = emulation.seed {
+                system.seed(seed);
             }
+            return Ok(Box::new(system));
+        }
+        Backend::Vip {
+            interpreter,
+            monitor,
+        } => (interpreter, monitor),
+    };
+    let read =
+        |path: &Path| fs::read(path).with_context(|| format!("could not read {}", path.display()));
+    let monitor = monitor.as_deref().map(read).transpose()?;
+    Ok(Box::new(Vip::init(monitor, &read(interpreter)?, rom)?))
+}
 
-            _ => system.execute(&op_code),
+fn run(args: RunArgs) -> Result<()> {
+    let config = Config::load(args.config.clone())?;
+    match &args.rom {
+        Some(path) if !path.is_dir() => play(&args, &config, path),
+        Some(dir) => launch(&args, &config, dir),
+        None => launch(&args, &config, &config.rom_dir()),
+    }
+}
+
+// picks ROMs from `dir` to play until the launcher is quit
+fn launch(args: &RunArgs, config: &Config, dir: &Path) -> Result<()> {
+    let mut launcher = Launcher::new(dir)?;
+    while let Some(rom_path) = launcher.pick(config, args)? {
+        if let Err(err) = play(args, config, &rom_path) {
+            launcher.message(format!("{}: {:#}", rom_path.display(), err));
         }
     }
+    Ok(())
+}
+
+fn play(args: &RunArgs, config: &Config, rom_path: &Path) -> Result<()> {
+    let rom = Rom::read(rom_path)?;
+    let settings = config.settings(&config::rom_hash(&rom.data), args.overrides())?;
+    let rom = args.emulation.place(rom, settings.load_address);
+    let rom_name = match rom_path.file_stem() {
+        Some(stem) if rom_path != Path::new("-") => stem.to_string_lossy().into_owned(),
+        _ => "chip8".to_string(),
+    };
+
+    let mut system = load(&rom, &settings, &args.emulation)?;
+    let mut recorder = match &args.record {
+        Some(path) => Some(record(path, &settings, system.height())?),
+        None => None,
+    };
+
+    let title = settings.title.clone().unwrap_or_else(|| rom_name.clone());
+    let mut display = Display::init(settings.render, settings.scale, settings.theme)?
+        .screenshots(settings.save_dir.clone(), rom_name)
+        .phosphor(settings.phosphor)
+        .rows(system.height())
+        .status_bar(
+            settings.status_bar,
+            &title,
+            settings.quirks.name().unwrap_or("custom"),
+        );
+    let mut input = TerminalInput::new(settings.keymap.clone(), settings.hotkeys.clone())?;
+    let options = RunOptions {
+        timing: settings.timing,
+        realtime: true,
+        max_frames: None,
+        turbo: settings.turbo,
+    };
+
+    let result = {
+        let mut video = Fanout::new().with(&mut display);
+        if let Some(recorder) = recorder.as_mut() {
+            video = video.with(recorder);
+        }
+        let mut bell = Bell::default();
+        let mut mute = Headless::new();
+        let audio: &mut dyn AudioSink = if settings.audio { &mut bell } else { &mut mute };
+        frontend::run(system.as_mut(), &mut video, &mut input, audio, options)
+    };
+    drop(display);
+    if let Some(recorder) = recorder {
+        recorder.finish()?;
+    }
+    result
+        .map(|_| ())
+        .map_err(|err| crash_report(err, system.as_ref()))
+}
+
+// adds the calls in progress to a fault, innermost first
+fn crash_report(err: anyhow::Error, system: &dyn Machine) -> anyhow::Error {
+    if err.downcast_ref::<Fault>().is_none() || system.stack().is_empty() {
+        return err;
+    }
+    let calls: Vec<_> = system
+        .stack()
+        .iter()
+        .rev()
+        .map(|ret| format!("  called from {:#05X}", ret.wrapping_sub(2) & 0x0FFF))
+        .collect();
+    anyhow!("{:#}\n{}", err, calls.join("\n"))
+}
 
-    Display::destroy()?;
+fn record(path: &Path, settings: &Settings, rows: usize) -> Result<Recorder> {
+    let recorder = Recorder::create(path, &settings.theme, rows)?;
+    Ok(match settings.phosphor {
+        0 => recorder,
+        decay => recorder.phosphor(decay),
+    })
+}
+
+fn disasm(rom_path: &Path) -> Result<()> {
+    let rom = Rom::read(rom_path)?;
+    write_stdout(&asm::disassemble(&rom.data, rom.load_address))
+}
+
+fn asm(source_path: &Path, output: Option<PathBuf>) -> Result<()> {
+    let source = fs::read_to_string(source_path)
+        .with_context(|| format!("could not read {}", source_path.display()))?;
+    let rom = asm::assemble(&source, chip8::ROM_START)
+        .with_context(|| format!("could not assemble {}", source_path.display()))?;
+    let output = output.unwrap_or_else(|| source_path.with_extension("ch8"));
+    fs::write(&output, &rom).with_context(|| format!("could not write {}", output.display()))?;
+    println!("wrote {} bytes to {}", rom.len(), output.display());
+    Ok(())
+}
+
+fn info(rom_path: &Path) -> Result<()> {
+    let rom = Rom::read(rom_path)?;
+    println!("file: {}", rom_path.display());
+    println!("size: {} bytes", rom.data.len());
+    println!("load: {:#05X}", rom.load_address);
+    let hash = config::rom_hash(&rom.data);
+    println!("sha1: {}", hash);
+
+    let Some(entry) = Database::embedded().lookup(&hash) else {
+        println!("not in the ROM database");
+        return Ok(());
+    };
+    let (program, info) = (entry.program, entry.rom);
+    println!("title: {}", program.title);
+    if !program.authors.is_empty() {
+        println!("authors: {}", program.authors.join(", "));
+    }
+    if let Some(release) = &program.release {
+        println!("release: {}", release);
+    }
+    if !info.platforms.is_empty() {
+        println!("platforms: {}", info.platforms.join(", "));
+    }
+    if let Some(quirks) = entry.quirks() {
+        println!("quirks: {}", quirks.name().unwrap_or("custom"));
+    }
+    if let Some(tickrate) = info.tickrate {
+        println!("ipf: {}", tickrate);
+    }
+    if !info.keys.is_empty() {
+        let mut keys: Vec<_> = info.keys.iter().collect();
+        keys.sort();
+        let keys: Vec<_> = keys
+            .into_iter()
+            .map(|(hint, key)| format!("{}={:X}", hint, key))
+            .collect();
+        println!("keys: {}", keys.join(" "));
+    }
+    if let Some(colors) = &info.colors {
+        println!("colors: {}", colors.pixels.join(" "));
+    }
+    if let Some(description) = &program.description {
+        println!("\n{}", description);
+    }
     Ok(())
 }
+
+fn test(
+    rom_path: &Path,
+    frames: u64,
+    record_path: Option<&Path>,
+    emulation: &EmulationArgs,
+) -> Result<()> {
+    // ignores the config file so results only depend on the flags
+    let rom = Rom::read(rom_path)?;
+    let settings =
+        Config::default().settings(&config::rom_hash(&rom.data), emulation.overrides())?;
+    let rom = emulation.place(rom, settings.load_address);
+    let mut system = load(&rom, &settings, emulation)?;
+    let mut recorder = match record_path {
+        Some(path) => Some(record(path, &settings, system.height())?),
+        None => None,
+    };
+
+    let mut headless = Headless::new();
+    let mut input = Headless::new();
+    let mut audio = Headless::new();
+    let mut video = Fanout::new().with(&mut headless);
+    if let Some(recorder) = recorder.as_mut() {
+        video = video.with(recorder);
+    }
+    let options = RunOptions {
+        timing: settings.timing,
+        realtime: false,
+        max_frames: Some(frames),
+        turbo: 0,
+    };
+    frontend::run(system.as_mut(), &mut video, &mut input, &mut audio, options)
+        .map_err(|err| crash_report(err, system.as_ref()))?;
+    if let Some(recorder) = recorder {
+        recorder.finish()?;
+    }
+
+    let mut screen = String::new();
+    for row in &system.frame_buffer()[..system.height()] {
+        screen.extend(row.iter().map(|px| if *px { '#' } else { '.' }));
+        screen.push('\n');
+    }
+    write_stdout(&screen)
+}
+
+// runs the core alone, without rendering, until it has executed `cycles` instructions
+fn bench(rom_path: &Path, cycles: u64, emulation: &EmulationArgs) -> Result<()> {
+    let rom = Rom::read(rom_path)?;
+    let settings =
+        Config::default().settings(&config::rom_hash(&rom.data), emulation.overrides())?;
+    let rom = emulation.place(rom, settings.load_address);
+    let mut system = load(&rom, &settings, emulation)?;
+
+    let start = Instant::now();
+    let mut frames = 0;
+    while system.instructions() < cycles {
+        system
+            .step_frame(settings.timing)
+            .map_err(|err| crash_report(err.into(), system.as_ref()))?;
+        frames += 1;
+    }
+    let elapsed = start.elapsed();
+
+    let instructions = system.instructions();
+    let per_second = instructions as f64 / elapsed.as_secs_f64();
+    write_stdout(&format!(
+        "{} instructions in {} frames took {:.3}s: {:.2}M instructions per second, {:.0} frames per second\n",
+        instructions,
+        frames,
+        elapsed.as_secs_f64(),
+        per_second / 1e6,
+        frames as f64 / elapsed.as_secs_f64()
+    ))
+}
+
+// unlike print! this doesn't panic when piped into something like `head`
+fn write_stdout(text: &str) -> Result<()> {
+    match io::stdout().write_all(text.as_bytes()) {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OpCode {
     Cls,
     Ret,
@@ -31,6 +31,19 @@ pub enum OpCode {
     LdVxK(usize),
     LdDtVx(usize),
     LdStVx(usize),
+    Skp(usize),
+    Sknp(usize),
+    LdVxDt(usize),
+    LdFVx(usize),
+    LdBVx(usize),
+    // CHIP-8X, see `decode_chip8x`
+    StepBg,
+    AddNibbles { vx: usize, vy: usize },
+    Colour { vx: usize, vy: usize, n: usize },
+    SkpKey2(usize),
+    SknpKey2(usize),
+    OutVx(usize),
+    InVx(usize),
     Unknown,
 }
 
@@ -65,9 +78,23 @@ impl Display for OpCode {
             OpCode::LdVxK(vx) => write!(f, "LD VX:{:#06X} K", vx),
             OpCode::LdDtVx(vx) => write!(f, "LD DT VX:{:#06X}", vx),
             OpCode::LdStVx(vx) => write!(f, "LD ST VX:{:#06X}", vx),
+            OpCode::Skp(vx) => write!(f, "SKP VX:{:#06X}", vx),
+            OpCode::Sknp(vx) => write!(f, "SKNP VX:{:#06X}", vx),
+            OpCode::LdVxDt(vx) => write!(f, "LD VX:{:#06X} DT", vx),
+            OpCode::LdFVx(vx) => write!(f, "LD F VX:{:#06X}", vx),
+            OpCode::LdBVx(vx) => write!(f, "LD B VX:{:#06X}", vx),
             OpCode::Drw { vx, vy, n } => {
                 write!(f, "DRW VX:{:#06X} VX:{:#06X} n:{:#06X}", vx, vy, n)
             }
+            OpCode::StepBg => write!(f, "BG"),
+            OpCode::AddNibbles { vx, vy } => write!(f, "ADDN VX:{:#06X} VY:{:#06X}", vx, vy),
+            OpCode::Colour { vx, vy, n } => {
+                write!(f, "COL VX:{:#06X} VY:{:#06X} n:{:#06X}", vx, vy, n)
+            }
+            OpCode::SkpKey2(vx) => write!(f, "SKP2 VX:{:#06X}", vx),
+            OpCode::SknpKey2(vx) => write!(f, "SKNP2 VX:{:#06X}", vx),
+            OpCode::OutVx(vx) => write!(f, "OUT VX:{:#06X}", vx),
+            OpCode::InVx(vx) => write!(f, "IN VX:{:#06X}", vx),
             OpCode::Unknown => write!(f, "Unknown"),
         }
     }
@@ -157,11 +184,19 @@ pub fn decode(op: u16) -> OpCode {
                 vy: ((op & 0x00F0) >> 4) as usize,
                 n: (op & 0x000F) as usize,
             },
+            0xE000 => match op & 0x00FF {
+                0x009E => OpCode::Skp(((op & 0x0F00) >> 8) as usize),
+                0x00A1 => OpCode::Sknp(((op & 0x0F00) >> 8) as usize),
+                _ => OpCode::Unknown,
+            },
             0xF000 => match op & 0x00FF {
+                0x0007 => OpCode::LdVxDt(((op & 0x0F00) >> 8) as usize),
                 0x000A => OpCode::LdVxK(((op & 0x0F00) >> 8) as usize),
                 0x001E => OpCode::AddIVx(((op & 0x0F00) >> 8) as usize),
                 0x0015 => OpCode::LdDtVx(((op & 0x0F00) >> 8) as usize),
                 0x0018 => OpCode::LdStVx(((op & 0x0F00) >> 8) as usize),
+                0x0029 => OpCode::LdFVx(((op & 0x0F00) >> 8) as usize),
+                0x0033 => OpCode::LdBVx(((op & 0x0F00) >> 8) as usize),
                 0x0055 => OpCode::LdIVx(((op & 0x0F00) >> 8) as usize),
                 0x0065 => OpCode::LdVxI(((op & 0x0F00) >> 8) as usize),
                 _ => OpCode::Unknown,
@@ -171,6 +206,79 @@ pub fn decode(op: u16) -> OpCode {
     }
 }
 
+/// Decodes CHIP-8X, where BXYN colours the screen instead of jumping and a few
+/// instructions are added for the colour board, a second keypad and the sound board.
+pub fn decode_chip8x(op: u16) -> OpCode {
+    let vx = ((op & 0x0F00) >> 8) as usize;
+    let vy = ((op & 0x00F0) >> 4) as usize;
+    match op & 0xF00F {
+        _ if op == 0x02A0 => OpCode::StepBg,
+        0x5001 => OpCode::AddNibbles { vx, vy },
+        0xB000..=0xB00F => OpCode::Colour {
+            vx,
+            vy,
+            n: (op & 0x000F) as usize,
+        },
+        _ => match op & 0xF0FF {
+            0xE0F2 => OpCode::SkpKey2(vx),
+            0xE0F5 => OpCode::SknpKey2(vx),
+            0xF0F8 => OpCode::OutVx(vx),
+            0xF0FB => OpCode::InVx(vx),
+            _ => decode(op),
+        },
+    }
+}
+
+/// The inverse of `decode` and `decode_chip8x`. `Unknown` has no encoding of its own and becomes 0x0000.
+pub fn encode(op: &OpCode) -> u16 {
+    let x = |vx: usize| (vx as u16 & 0x000F) << 8;
+    let xy = |vx: usize, vy: usize| x(vx) | (vy as u16 & 0x000F) << 4;
+    match *op {
+        OpCode::Cls => 0x00E0,
+        OpCode::Ret => 0x00EE,
+        OpCode::Jmp(addr) => 0x1000 | addr & 0x0FFF,
+        OpCode::Call(addr) => 0x2000 | addr & 0x0FFF,
+        OpCode::Se { vx, value } => 0x3000 | x(vx) | value as u16,
+        OpCode::Sne { vx, value } => 0x4000 | x(vx) | value as u16,
+        OpCode::SeVxVy { vx, vy } => 0x5000 | xy(vx, vy),
+        OpCode::LdVx { vx, value } => 0x6000 | x(vx) | value as u16,
+        OpCode::AddVx { vx, value } => 0x7000 | x(vx) | value as u16,
+        OpCode::LdVxVy { vx, vy } => 0x8000 | xy(vx, vy),
+        OpCode::OrVxVy { vx, vy } => 0x8001 | xy(vx, vy),
+        OpCode::AndVxVy { vx, vy } => 0x8002 | xy(vx, vy),
+        OpCode::XorVxVy { vx, vy } => 0x8003 | xy(vx, vy),
+        OpCode::AddVxVy { vx, vy } => 0x8004 | xy(vx, vy),
+        OpCode::Sub { vx, vy } => 0x8005 | xy(vx, vy),
+        OpCode::Shr { vx, vy } => 0x8006 | xy(vx, vy),
+        OpCode::SubN { vx, vy } => 0x8007 | xy(vx, vy),
+        OpCode::Shl { vx, vy } => 0x800E | xy(vx, vy),
+        OpCode::SneVxVy { vx, vy } => 0x9000 | xy(vx, vy),
+        OpCode::LdI(addr) => 0xA000 | addr & 0x0FFF,
+        OpCode::JmpV0(addr) => 0xB000 | addr & 0x0FFF,
+        OpCode::Rnd { vx, value } => 0xC000 | x(vx) | value as u16,
+        OpCode::Drw { vx, vy, n } => 0xD000 | xy(vx, vy) | (n as u16 & 0x000F),
+        OpCode::Skp(vx) => 0xE09E | x(vx),
+        OpCode::Sknp(vx) => 0xE0A1 | x(vx),
+        OpCode::LdVxDt(vx) => 0xF007 | x(vx),
+        OpCode::LdVxK(vx) => 0xF00A | x(vx),
+        OpCode::LdDtVx(vx) => 0xF015 | x(vx),
+        OpCode::LdStVx(vx) => 0xF018 | x(vx),
+        OpCode::AddIVx(vx) => 0xF01E | x(vx),
+        OpCode::LdFVx(vx) => 0xF029 | x(vx),
+        OpCode::LdBVx(vx) => 0xF033 | x(vx),
+        OpCode::LdIVx(vx) => 0xF055 | x(vx),
+        OpCode::LdVxI(vx) => 0xF065 | x(vx),
+        OpCode::StepBg => 0x02A0,
+        OpCode::AddNibbles { vx, vy } => 0x5001 | xy(vx, vy),
+        OpCode::Colour { vx, vy, n } => 0xB000 | xy(vx, vy) | (n as u16 & 0x000F),
+        OpCode::SkpKey2(vx) => 0xE0F2 | x(vx),
+        OpCode::SknpKey2(vx) => 0xE0F5 | x(vx),
+        OpCode::OutVx(vx) => 0xF0F8 | x(vx),
+        OpCode::InVx(vx) => 0xF0FB | x(vx),
+        OpCode::Unknown => 0x0000,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -409,4 +517,66 @@ mod tests {
             result
         );
     }
+
+    #[test]
+    fn skp() {
+        let result = decode(0xE19E);
+        assert_eq!(OpCode::Skp(0x0001), result);
+    }
+
+    #[test]
+    fn sknp() {
+        let result = decode(0xE2A1);
+        assert_eq!(OpCode::Sknp(0x0002), result);
+    }
+
+    #[test]
+    fn ld_vx_dt() {
+        let result = decode(0xF307);
+        assert_eq!(OpCode::LdVxDt(0x0003), result);
+    }
+
+    #[test]
+    fn ld_f_vx() {
+        let result = decode(0xF429);
+        assert_eq!(OpCode::LdFVx(0x0004), result);
+    }
+
+    #[test]
+    fn ld_b_vx() {
+        let result = decode(0xF533);
+        assert_eq!(OpCode::LdBVx(0x0005), result);
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        for op in [
+            0x00E0, 0x00EE, 0x1ABC, 0x2ABC, 0x31AB, 0x41AB, 0x51A0, 0x61AB, 0x7234, 0x8A10, 0x8A11,
+            0x8A12, 0x8A13, 0x8A14, 0x8A15, 0x8A16, 0x8A17, 0x8A1E, 0x9A10, 0xA123, 0xB123, 0xC1FF,
+            0xDAB1, 0xE19E, 0xE2A1, 0xF307, 0xF10A, 0xF215, 0xF429, 0xF533, 0xF318, 0xF41E, 0xF555,
+            0xF665,
+        ] {
+            assert_eq!(op, encode(&decode(op)), "{:#06X}", op);
+        }
+    }
+
+    #[test]
+    fn chip8x() {
+        assert_eq!(OpCode::JmpV0(0x0123), decode(0xB123), "only in CHIP-8X");
+        assert_eq!(
+            OpCode::Colour {
+                vx: 0x0001,
+                vy: 0x0002,
+                n: 0x0003
+            },
+            decode_chip8x(0xB123)
+        );
+        assert_eq!(OpCode::SeVxVy { vx: 1, vy: 2 }, decode_chip8x(0x5120));
+        assert_eq!(OpCode::Jmp(0x0ABC), decode_chip8x(0x1ABC));
+        for op in [
+            0x02A0, 0x5121, 0xB120, 0xB12F, 0xE3F2, 0xE3F5, 0xF4F8, 0xF4FB,
+        ] {
+            assert_eq!(op, encode(&decode_chip8x(op)), "{:#06X}", op);
+        }
+    }
 }
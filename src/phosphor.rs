@@ -0,0 +1,86 @@
+use crate::emulator::FrameBuffer;
+
+/// Brightness of each pixel, from 0 for off to 255 for fully lit.
+pub type Shades = [[u8; 64]; 64];
+
+/// The frame buffer with lit pixels at full brightness and no afterglow.
+pub fn shades(frame_buffer: &FrameBuffer) -> Shades {
+    let mut shades = [[0; 64]; 64];
+    for (row, pixels) in shades.iter_mut().zip(frame_buffer) {
+        for (shade, px) in row.iter_mut().zip(pixels) {
+            *shade = if *px { 255 } else { 0 };
+        }
+    }
+    shades
+}
+
+/// Simulates the afterglow of a CRT. Pixels light up in the frame they are drawn and
+/// fade out over a number of frames once they are turned off, which hides the
+/// flicker of sprites being erased in one frame and redrawn in the next to move.
+#[derive(Debug, Clone)]
+pub struct Phosphor {
+    // brightness lost per frame
+    step: u8,
+    shades: Shades,
+}
+
+impl Phosphor {
+    /// Fades pixels out over `decay` frames, 0 turns them off straight away.
+    pub fn new(decay: u8) -> Phosphor {
+        Phosphor {
+            step: match decay {
+                0 => 255,
+                decay => 255u8.div_ceil(decay),
+            },
+            shades: [[0; 64]; 64],
+        }
+    }
+
+    /// Fades pixels that are off by a frame's worth. Call once per 60Hz frame.
+    pub fn end_frame(&mut self, frame_buffer: &FrameBuffer) -> &Shades {
+        for (row, pixels) in self.shades.iter_mut().zip(frame_buffer) {
+            for (shade, px) in row.iter_mut().zip(pixels) {
+                *shade = if *px {
+                    255
+                } else {
+                    shade.saturating_sub(self.step)
+                };
+            }
+        }
+        &self.shades
+    }
+
+    pub fn shades(&self) -> &Shades {
+        &self.shades
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_out() {
+        let mut phosphor = Phosphor::new(3);
+        let mut frame_buffer = [[false; 64]; 64];
+        frame_buffer[0][0] = true;
+
+        assert_eq!(255, phosphor.end_frame(&frame_buffer)[0][0]);
+        frame_buffer[0][0] = false;
+        assert_eq!(170, phosphor.end_frame(&frame_buffer)[0][0]);
+        assert_eq!(85, phosphor.end_frame(&frame_buffer)[0][0]);
+        assert_eq!(0, phosphor.end_frame(&frame_buffer)[0][0]);
+    }
+
+    #[test]
+    fn no_decay() {
+        let mut phosphor = Phosphor::new(0);
+        let mut frame_buffer = [[false; 64]; 64];
+        frame_buffer[0][0] = true;
+        phosphor.end_frame(&frame_buffer);
+
+        frame_buffer[0][0] = false;
+
+        assert_eq!(shades(&frame_buffer), *phosphor.end_frame(&frame_buffer));
+    }
+}
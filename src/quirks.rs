@@ -0,0 +1,114 @@
+/// Behaviours that differ between CHIP-8 interpreters. ROMs written for one
+/// interpreter often misbehave on another, so these are chosen per ROM.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quirks {
+    /// 8XY1, 8XY2 and 8XY3 reset VF to 0.
+    pub vf_reset: bool,
+    /// FX55 and FX65 leave I pointing past the last register stored/loaded.
+    pub memory: bool,
+    /// 8XY6 and 8XYE shift VX in place instead of shifting VY into VX.
+    pub shifting: bool,
+    /// BNNN jumps to XNN + VX instead of NNN + V0.
+    pub jumping: bool,
+    /// sprites are clipped at the edges of the screen instead of wrapping around.
+    pub clipping: bool,
+    /// DXYN waits for the next 60Hz frame, so at most one sprite is drawn per frame.
+    pub display_wait: bool,
+    /// addresses past 0xFFF wrap around to the start of memory like on the VIP, instead
+    /// of faulting.
+    pub address_wrap: bool,
+    /// how many return addresses the call stack holds, `None` for no limit.
+    pub stack_depth: Option<usize>,
+    /// CHIP-8X: BXYN colours the screen instead of jumping, and 02A0, 5XY1, EXF2, EXF5,
+    /// FXF8 and FXFB drive the colour board, a second keypad and the sound board.
+    pub chip8x: bool,
+}
+
+impl Default for Quirks {
+    // what this emulator has always done
+    fn default() -> Self {
+        Quirks {
+            vf_reset: false,
+            memory: false,
+            shifting: true,
+            jumping: false,
+            clipping: true,
+            display_wait: false,
+            address_wrap: false,
+            stack_depth: Some(16),
+            chip8x: false,
+        }
+    }
+}
+
+impl Quirks {
+    pub const PROFILES: [&'static str; 5] = ["modern", "vip", "chip8x", "schip", "xochip"];
+
+    pub fn vip() -> Quirks {
+        Quirks {
+            vf_reset: true,
+            memory: true,
+            shifting: false,
+            jumping: false,
+            clipping: true,
+            display_wait: true,
+            address_wrap: true,
+            stack_depth: Some(12),
+            chip8x: false,
+        }
+    }
+
+    /// The VIP with the CHIP-8X interpreter, which was built on the original.
+    pub fn chip8x() -> Quirks {
+        Quirks {
+            chip8x: true,
+            ..Quirks::vip()
+        }
+    }
+
+    pub fn schip() -> Quirks {
+        Quirks {
+            vf_reset: false,
+            memory: false,
+            shifting: true,
+            jumping: true,
+            clipping: true,
+            display_wait: false,
+            address_wrap: true,
+            stack_depth: Some(16),
+            chip8x: false,
+        }
+    }
+
+    pub fn xochip() -> Quirks {
+        Quirks {
+            vf_reset: false,
+            memory: true,
+            shifting: false,
+            jumping: false,
+            clipping: false,
+            display_wait: false,
+            address_wrap: true,
+            stack_depth: Some(16),
+            chip8x: false,
+        }
+    }
+
+    /// The name of the profile these quirks match, if any.
+    pub fn name(&self) -> Option<&'static str> {
+        Quirks::PROFILES
+            .into_iter()
+            .find(|name| Quirks::profile(name) == Some(*self))
+    }
+
+    pub fn profile(name: &str) -> Option<Quirks> {
+        match name.to_lowercase().as_str() {
+            "modern" => Some(Quirks::default()),
+            "vip" | "chip8" => Some(Quirks::vip()),
+            "chip8x" | "chip-8x" => Some(Quirks::chip8x()),
+            "schip" | "superchip" => Some(Quirks::schip()),
+            "xochip" | "xo-chip" => Some(Quirks::xochip()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use crate::emulator::FrameBuffer;
+use crate::frontend::VideoSink;
+use crate::phosphor::{self, Phosphor, Shades};
+use crate::theme::Theme;
+use anyhow::{Context, Result};
+use gif::{Encoder, Frame, Repeat};
+use std::{fs::File, io::BufWriter, path::Path};
+
+// each CHIP-8 pixel becomes a SCALE x SCALE square in the recording
+const SCALE: usize = 4;
+// shades between the background and foreground in the GIF palette
+const LEVELS: usize = 16;
+
+/// Records every frame to an animated GIF. Runs of identical frames are merged
+/// into one longer GIF frame.
+pub struct Recorder {
+    encoder: Encoder<BufWriter<File>>,
+    phosphor: Option<Phosphor>,
+    last: Option<Shades>,
+    // rows of the screen recorded, 64 in hi-res mode
+    rows: usize,
+    // 60Hz frames the last frame has been on screen
+    repeats: u32,
+    // centiseconds owed to rounding, GIF delays are in 1/100ths of a second
+    remainder: u32,
+}
+
+impl Recorder {
+    /// Records the top `rows` of the screen to `path` in the theme's colours.
+    pub fn create(path: &Path, theme: &Theme, rows: usize) -> Result<Recorder> {
+        let file =
+            File::create(path).with_context(|| format!("could not create {}", path.display()))?;
+        let palette: Vec<u8> = (0..LEVELS)
+            .flat_map(|level| theme.shade((level * 255 / (LEVELS - 1)) as u8))
+            .collect();
+        let mut encoder = Encoder::new(
+            BufWriter::new(file),
+            (64 * SCALE) as u16,
+            (rows * SCALE) as u16,
+            &palette,
+        )?;
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(Recorder {
+            encoder,
+            phosphor: None,
+            last: None,
+            rows,
+            repeats: 0,
+            remainder: 0,
+        })
+    }
+
+    /// Records with pixels fading out over `decay` frames, see [`Phosphor`].
+    pub fn phosphor(mut self, decay: u8) -> Recorder {
+        self.phosphor = Some(Phosphor::new(decay));
+        self
+    }
+
+    fn write_last(&mut self) -> Result<()> {
+        let Some(shades) = self.last else {
+            return Ok(());
+        };
+        let mut pixels = Vec::with_capacity(64 * self.rows * SCALE * SCALE);
+        for row in &shades[..self.rows] {
+            for _ in 0..SCALE {
+                for shade in row {
+                    // round to the nearest level in the palette
+                    let level = (*shade as usize * (LEVELS - 1) + 127) / 255;
+                    pixels.extend([level as u8; SCALE]);
+                }
+            }
+        }
+        let mut frame = Frame::from_indexed_pixels(
+            (64 * SCALE) as u16,
+            (self.rows * SCALE) as u16,
+            pixels,
+            None,
+        );
+        let centiseconds = self.repeats * 100 + self.remainder;
+        frame.delay = (centiseconds / 60) as u16;
+        self.remainder = centiseconds % 60;
+        self.encoder.write_frame(&frame)?;
+        Ok(())
+    }
+
+    /// Writes out the final frame. Dropping the recorder without finishing loses it.
+    pub fn finish(mut self) -> Result<()> {
+        self.write_last()
+    }
+}
+
+impl VideoSink for Recorder {
+    // frames are recorded at their end, changed or not
+    fn present(&mut self, _frame_buffer: &FrameBuffer) -> Result<()> {
+        Ok(())
+    }
+
+    fn end_frame(&mut self, frame_buffer: &FrameBuffer) -> Result<()> {
+        let shades = match self.phosphor.as_mut() {
+            Some(phosphor) => *phosphor.end_frame(frame_buffer),
+            None => phosphor::shades(frame_buffer),
+        };
+        if self.last == Some(shades) && self.repeats < u16::MAX as u32 {
+            self.repeats += 1;
+            return Ok(());
+        }
+        self.write_last()?;
+        self.last = Some(shades);
+        self.repeats = 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::HEIGHT;
+    use std::fs;
+
+    #[test]
+    fn records_gif() {
+        let path = std::env::temp_dir().join(format!("chip8-recorder-{}.gif", std::process::id()));
+        let mut recorder = Recorder::create(&path, &Theme::default(), HEIGHT)
+            .unwrap()
+            .phosphor(4);
+        let mut frame_buffer = [[false; 64]; 64];
+
+        recorder.end_frame(&frame_buffer).unwrap();
+        recorder.end_frame(&frame_buffer).unwrap();
+        frame_buffer[0][0] = true;
+        recorder.end_frame(&frame_buffer).unwrap();
+        recorder.finish().unwrap();
+
+        let gif = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(b"GIF89a", &gif[..6]);
+        assert_eq!(Some(&0x3B), gif.last(), "should end with the trailer");
+    }
+}
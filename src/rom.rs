@@ -0,0 +1,326 @@
+use crate::heap;
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+/// The first instruction of two-page hi-res programs, a jump to the 1802 code after
+/// it that switches the VIP to a 64x64 display.
+const HIRES_HEADER: [u8; 2] = [0x12, 0x60];
+// the header and that code, which this emulator does itself
+const HIRES_SETUP: u16 = 0xC0;
+
+/// How a ROM file is encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// the program bytes as-is: .ch8, .c8, .sc8, .xo8 and friends
+    Raw,
+    /// Intel HEX records, which carry their own load address
+    IntelHex,
+    /// program bytes written out as text, e.g. "00 E0 A2 2A"
+    HexDump,
+}
+
+impl Format {
+    /// The format of a file going by its extension, if it is one ROMs use.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "ch8" | "c8" | "sc8" | "xo8" | "bin" => Some(Format::Raw),
+            "hex" | "ihx" => Some(Format::IntelHex),
+            "txt" => Some(Format::HexDump),
+            _ => None,
+        }
+    }
+
+    // a guess for stdin and unknown extensions
+    fn sniff(data: &[u8]) -> Format {
+        let text = match std::str::from_utf8(data) {
+            Ok(text) => text.trim_start(),
+            Err(_) => return Format::Raw,
+        };
+        if text.starts_with(':') {
+            Format::IntelHex
+        } else if !text.is_empty() && parse_hex_dump(text).is_ok() {
+            Format::HexDump
+        } else {
+            Format::Raw
+        }
+    }
+}
+
+/// A program and where it goes in memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rom {
+    pub data: Vec<u8>,
+    pub load_address: u16,
+    /// where execution starts, usually the load address
+    pub entry: u16,
+    /// runs in the two-page 64x64 hi-res mode
+    pub hires: bool,
+}
+
+impl Rom {
+    /// A raw program loaded and started at 0x200, or at 0x2C0 for a hi-res program.
+    pub fn new(data: Vec<u8>) -> Rom {
+        let rom = Rom {
+            data,
+            load_address: heap::ROM_START,
+            entry: heap::ROM_START,
+            hires: false,
+        };
+        match rom.data.starts_with(&HIRES_HEADER) {
+            true => rom.hires(),
+            false => rom,
+        }
+    }
+
+    /// Reads a ROM from a file, or from stdin when the path is `-`. The format comes
+    /// from the extension, or is guessed from the contents.
+    pub fn read(path: &Path) -> Result<Rom> {
+        let data = if path == Path::new("-") {
+            let mut data = Vec::new();
+            io::stdin()
+                .read_to_end(&mut data)
+                .context("could not read ROM from stdin")?;
+            data
+        } else {
+            fs::read(path).with_context(|| format!("could not read ROM {}", path.display()))?
+        };
+        let format = Format::from_path(path).unwrap_or_else(|| Format::sniff(&data));
+        Rom::parse(&data, format).with_context(|| format!("invalid ROM {}", path.display()))
+    }
+
+    pub fn parse(data: &[u8], format: Format) -> Result<Rom> {
+        match format {
+            Format::Raw => Ok(Rom::new(data.to_vec())),
+            Format::IntelHex => parse_intel_hex(as_text(data)?),
+            Format::HexDump => Ok(Rom::new(parse_hex_dump(as_text(data)?)?)),
+        }
+    }
+
+    /// Moves the ROM, e.g. to 0x600 for the ETI-660. The entry point moves with it.
+    pub fn at(mut self, load_address: u16) -> Rom {
+        self.load_address = load_address;
+        self.entry = load_address;
+        match self.hires {
+            true => self.hires(),
+            false => self,
+        }
+    }
+
+    /// Runs the ROM in the two-page 64x64 hi-res mode. A ROM with the usual 0x1260
+    /// header starts after the setup code that follows it.
+    pub fn hires(mut self) -> Rom {
+        self.hires = true;
+        if self.data.starts_with(&HIRES_HEADER) {
+            self.entry = self.load_address + HIRES_SETUP;
+        }
+        self
+    }
+
+    pub fn entry(mut self, entry: u16) -> Rom {
+        self.entry = entry;
+        self
+    }
+
+    /// Checks the ROM fits in memory above the font and starts inside memory.
+    pub fn validate(&self) -> Result<()> {
+        let start = self.load_address as usize;
+        if start < heap::FONT_END {
+            bail!(
+                "load address {:#05X} would overwrite the font, it must be at least {:#05X}",
+                start,
+                heap::FONT_END
+            );
+        }
+        let room = heap::MEMORY_SIZE.saturating_sub(start);
+        if self.data.len() > room {
+            bail!(
+                "ROM is {} bytes but only {} bytes fit in memory from {:#05X}",
+                self.data.len(),
+                room,
+                start
+            );
+        }
+        if self.entry as usize >= heap::MEMORY_SIZE {
+            bail!("entry point {:#X} is outside memory", self.entry);
+        }
+        Ok(())
+    }
+}
+
+fn as_text(data: &[u8]) -> Result<&str> {
+    std::str::from_utf8(data).map_err(|_| anyhow!("expected text but found binary data"))
+}
+
+// One hex byte after another. Lines may start with an address ending in ':',
+// and '#' or ';' start a comment.
+fn parse_hex_dump(text: &str) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split(['#', ';']).next().unwrap();
+        let line = match line.split_once(':') {
+            Some((_, bytes)) => bytes,
+            None => line,
+        };
+        for token in line.split(|c: char| c.is_whitespace() || c == ',') {
+            let token = token.trim_start_matches("0x").trim_start_matches("0X");
+            if token.is_empty() {
+                continue;
+            }
+            if token.len() % 2 != 0 {
+                bail!("line {}: {:?} is not a whole number of bytes", i + 1, token);
+            }
+            for pair in token.as_bytes().chunks(2) {
+                let pair = std::str::from_utf8(pair).unwrap();
+                let byte = u8::from_str_radix(pair, 16)
+                    .map_err(|_| anyhow!("line {}: {:?} is not hex", i + 1, token))?;
+                data.push(byte);
+            }
+        }
+    }
+    Ok(data)
+}
+
+fn parse_intel_hex(text: &str) -> Result<Rom> {
+    let mut records = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let record = parse_record(line).with_context(|| format!("line {}", i + 1))?;
+        match record.kind {
+            0x00 => records.push((record.address as usize, record.data)),
+            0x01 => break,
+            // a non-zero segment or upper address would be beyond 64K
+            0x02 | 0x04 if record.data.iter().all(|b| *b == 0) => {}
+            0x03 | 0x05 => {}
+            kind => bail!("line {}: unsupported record type {:02X}", i + 1, kind),
+        }
+    }
+
+    let start = records.iter().map(|(addr, _)| *addr).min().unwrap_or(0);
+    let end = records
+        .iter()
+        .map(|(addr, data)| addr + data.len())
+        .max()
+        .unwrap_or(0);
+    let mut data = vec![0; end - start];
+    for (addr, bytes) in records {
+        data[addr - start..addr - start + bytes.len()].copy_from_slice(&bytes);
+    }
+    if data.is_empty() {
+        return Ok(Rom::new(data));
+    }
+    Ok(Rom::new(data).at(start as u16))
+}
+
+struct Record {
+    kind: u8,
+    address: u16,
+    data: Vec<u8>,
+}
+
+// :LLAAAATT<data>CC
+fn parse_record(line: &str) -> Result<Record> {
+    let hex = line
+        .strip_prefix(':')
+        .ok_or_else(|| anyhow!("records must start with ':'"))?;
+    let bytes = parse_hex_dump(hex)?;
+    if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+        bail!("record length does not match its byte count");
+    }
+    let checksum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    if checksum != 0 {
+        bail!("bad checksum");
+    }
+    Ok(Record {
+        kind: bytes[3],
+        address: u16::from_be_bytes([bytes[1], bytes[2]]),
+        data: bytes[4..bytes.len() - 1].to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw() {
+        let rom = Rom::parse(&[0x00, 0xE0], Format::Raw).unwrap();
+
+        assert_eq!(vec![0x00, 0xE0], rom.data);
+        assert_eq!(0x200, rom.load_address);
+        assert_eq!(0x200, rom.entry);
+    }
+
+    #[test]
+    fn hex_dump() {
+        let text = "# clear the screen\n0200: 00 E0\n0x12, 0x00 ; loop\nA22A\n";
+
+        let rom = Rom::parse(text.as_bytes(), Format::HexDump).unwrap();
+
+        assert_eq!(vec![0x00, 0xE0, 0x12, 0x00, 0xA2, 0x2A], rom.data);
+        assert!(Rom::parse(b"00 E", Format::HexDump).is_err());
+        assert!(Rom::parse(b"00 ZZ", Format::HexDump).is_err());
+    }
+
+    #[test]
+    fn intel_hex() {
+        let text = ":0406000000E0160000\n:00000001FF\n";
+
+        let rom = Rom::parse(text.as_bytes(), Format::IntelHex).unwrap();
+
+        assert_eq!(vec![0x00, 0xE0, 0x16, 0x00], rom.data);
+        assert_eq!(0x600, rom.load_address, "should load where the records say");
+        assert_eq!(0x600, rom.entry);
+
+        let bad_checksum = ":0406000000E0160001\n";
+        assert!(Rom::parse(bad_checksum.as_bytes(), Format::IntelHex).is_err());
+    }
+
+    #[test]
+    fn hires() {
+        let rom = Rom::new(vec![0x12, 0x60, 0x00, 0xE0]);
+
+        assert!(rom.hires);
+        assert_eq!(0x200, rom.load_address);
+        assert_eq!(0x2C0, rom.entry, "should skip the setup code");
+
+        let rom = Rom::new(vec![0x00, 0xE0]).hires();
+        assert!(rom.hires);
+        assert_eq!(0x200, rom.entry, "should start as usual without the header");
+    }
+
+    #[test]
+    fn sniff() {
+        assert_eq!(Format::IntelHex, Format::sniff(b":00000001FF"));
+        assert_eq!(Format::HexDump, Format::sniff(b"00 E0 12 00"));
+        assert_eq!(Format::Raw, Format::sniff(&[0x00, 0xE0, 0x12, 0x00]));
+    }
+
+    #[test]
+    fn validate_size() {
+        assert!(Rom::new(vec![0; 3584]).validate().is_ok());
+
+        let err = Rom::new(vec![0; 3585]).validate().unwrap_err();
+        assert_eq!(
+            "ROM is 3585 bytes but only 3584 bytes fit in memory from 0x200",
+            err.to_string()
+        );
+
+        assert!(
+            Rom::new(vec![0; 3584]).at(0x600).validate().is_err(),
+            "less room at 0x600"
+        );
+        assert!(
+            Rom::new(vec![]).at(0x010).validate().is_err(),
+            "over the font"
+        );
+        assert!(Rom::new(vec![]).entry(0x1000).validate().is_err());
+    }
+}
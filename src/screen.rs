@@ -0,0 +1,372 @@
+use chip8::{
+    chip8x::{self, Colours},
+    emulator::HEIGHT,
+    phosphor::Shades,
+    Theme,
+};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+
+const WIDTH: u16 = 64;
+
+/// How CHIP-8 pixels are packed into terminal cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// `▀`/`▄`, 1x2 pixels per cell
+    HalfBlock,
+    /// braille dots, 2x4 pixels per cell
+    Braille,
+    /// quadrant blocks, 2x2 pixels per cell
+    Quadrant,
+    /// one full block per pixel, stretched to fill the terminal
+    Block,
+    /// `#` per pixel for terminals without Unicode
+    Ascii,
+}
+
+impl RenderMode {
+    pub const NAMES: [&'static str; 5] = ["halfblock", "braille", "quadrant", "block", "ascii"];
+
+    pub fn from_name(name: &str) -> Option<RenderMode> {
+        match name {
+            "halfblock" => Some(RenderMode::HalfBlock),
+            "braille" => Some(RenderMode::Braille),
+            "quadrant" => Some(RenderMode::Quadrant),
+            "block" => Some(RenderMode::Block),
+            "ascii" => Some(RenderMode::Ascii),
+            _ => None,
+        }
+    }
+
+    // sub-pixels per cell across and down
+    fn cell(self) -> (u16, u16) {
+        match self {
+            RenderMode::HalfBlock => (1, 2),
+            RenderMode::Braille => (2, 4),
+            RenderMode::Quadrant => (2, 2),
+            RenderMode::Block | RenderMode::Ascii => (1, 1),
+        }
+    }
+
+    // sub-pixels per CHIP-8 pixel at scale 1, so pixels come out roughly square
+    // given cells twice as tall as they are wide
+    fn pixel(self) -> (u16, u16) {
+        let (across, down) = self.cell();
+        (2 * across / down, 1)
+    }
+
+    /// Size in cells of the image of a screen `rows` high drawn into `area`. With a
+    /// `scale` the image is that many times its smallest size, otherwise it is as large
+    /// as fits. Either way it shrinks to fit rather than being clipped.
+    pub fn size(self, area: Rect, scale: Option<u16>, rows: usize) -> (u16, u16) {
+        let (across, down) = self.cell();
+        if self == RenderMode::Block {
+            return (area.width, area.height);
+        }
+        // sub-pixels, widened so large terminals don't overflow
+        let width = area.width as u32 * across as u32;
+        let height = area.height as u32 * down as u32;
+        let (pw, ph) = self.pixel();
+        let (pw, ph) = (WIDTH as u32 * pw as u32, rows as u32 * ph as u32);
+        let fits = (width / pw).min(height / ph);
+        let (width, height) = match scale.map_or(fits, |scale| (scale as u32).min(fits)) {
+            // too small even at scale 1, keep the aspect ratio and drop pixels
+            0 => {
+                let width = width.min(height * pw / ph);
+                (width, width * ph / pw)
+            }
+            scale => (pw * scale, ph * scale),
+        };
+        (
+            width.div_ceil(across as u32) as u16,
+            height.div_ceil(down as u32) as u16,
+        )
+    }
+}
+
+/// The screen as a widget, scaled to the area it is given. Pixels are drawn in shades
+/// of the theme so faded ones show up dimmer.
+pub struct Screen<'a> {
+    shades: &'a Shades,
+    mode: RenderMode,
+    theme: &'a Theme,
+    colours: Option<&'a Colours>,
+    rows: usize,
+}
+
+impl<'a> Screen<'a> {
+    pub fn new(shades: &'a Shades, mode: RenderMode, theme: &'a Theme) -> Screen<'a> {
+        Screen {
+            shades,
+            mode,
+            theme,
+            colours: None,
+            rows: HEIGHT,
+        }
+    }
+
+    /// Draws the top `rows` of the screen, 64 in hi-res mode.
+    pub fn rows(mut self, rows: usize) -> Screen<'a> {
+        self.rows = rows;
+        self
+    }
+
+    /// Draws CHIP-8X colours instead of the theme's.
+    pub fn colours(mut self, colours: Option<&'a Colours>) -> Screen<'a> {
+        self.colours = colours;
+        self
+    }
+}
+
+pub fn rgb([r, g, b]: [u8; 3]) -> Color {
+    Color::Rgb(r, g, b)
+}
+
+impl Widget for Screen<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (across, down) = self.mode.cell();
+        let (width, height) = (area.width * across, area.height * down);
+        if width == 0 || height == 0 {
+            return;
+        }
+        // nearest neighbour, which is exact when the area is a whole multiple
+        let pixel = |x: u16, y: u16| {
+            let px = (x as usize * WIDTH as usize) / width as usize;
+            let py = (y as usize * self.rows) / height as usize;
+            (px, py)
+        };
+        let shade = |x: u16, y: u16| {
+            let (px, py) = pixel(x, y);
+            self.shades[py][px]
+        };
+        // CHIP-8X colours each cell after the zone its top left pixel is in
+        let theme = |x: u16, y: u16| match self.colours {
+            Some(colours) => {
+                let (px, py) = pixel(x, y);
+                let mut theme = *self.theme;
+                theme.palette[0] = chip8x::rgb(colours.background());
+                theme.palette[1] = chip8x::rgb(colours.at(px, py));
+                theme
+            }
+            None => *self.theme,
+        };
+
+        for row in 0..area.height {
+            for col in 0..area.width {
+                let (x, y) = (col * across, row * down);
+                let theme = theme(x, y);
+                let color = |level: u8| rgb(theme.shade(level));
+                let cell = buf.get_mut(area.x + col, area.y + row);
+                cell.set_bg(rgb(theme.background()));
+                // the brightest pixel in the cell colours all of it, except for half
+                // blocks where the background can colour the bottom half
+                let mut brightest = 0;
+                let mut lit = |dx: u16, dy: u16| {
+                    let level = shade(x + dx, y + dy);
+                    brightest = brightest.max(level);
+                    level > 0
+                };
+                match self.mode {
+                    RenderMode::HalfBlock => {
+                        let (top, bottom) = (shade(x, y), shade(x, y + 1));
+                        let symbol = match (top > 0, bottom > 0) {
+                            (false, false) => ' ',
+                            (true, false) => '▀',
+                            (false, true) => '▄',
+                            (true, true) if top == bottom => '█',
+                            (true, true) => {
+                                cell.set_bg(color(bottom));
+                                '▀'
+                            }
+                        };
+                        cell.set_char(symbol)
+                            .set_fg(color(if top > 0 { top } else { bottom }));
+                        continue;
+                    }
+                    RenderMode::Quadrant => {
+                        let bits = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, (dx, dy))| lit(*dx, *dy))
+                            .fold(0, |bits, (bit, _)| bits | 1 << bit);
+                        cell.set_char(QUADRANTS[bits]);
+                    }
+                    RenderMode::Braille => {
+                        let dots = BRAILLE
+                            .iter()
+                            .filter(|(dx, dy, _)| lit(*dx, *dy))
+                            .fold(0, |dots, (_, _, dot)| dots | dot);
+                        cell.set_char(match dots {
+                            0 => ' ',
+                            dots => char::from_u32(0x2800 + dots).unwrap(),
+                        });
+                    }
+                    RenderMode::Block => {
+                        cell.set_char(if lit(0, 0) { '█' } else { ' ' });
+                    }
+                    RenderMode::Ascii => {
+                        cell.set_char(if lit(0, 0) { '#' } else { ' ' });
+                    }
+                }
+                cell.set_fg(color(brightest));
+            }
+        }
+    }
+}
+
+// indexed by top left, top right, bottom left, bottom right bits
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+// column, row and bit of each braille dot
+const BRAILLE: [(u16, u16, u32); 8] = [
+    (0, 0, 0x01),
+    (0, 1, 0x02),
+    (0, 2, 0x04),
+    (1, 0, 0x08),
+    (1, 1, 0x10),
+    (1, 2, 0x20),
+    (0, 3, 0x40),
+    (1, 3, 0x80),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chip8::{phosphor, FrameBuffer};
+
+    fn draw(frame_buffer: &FrameBuffer, mode: RenderMode, width: u16, height: u16) -> Buffer {
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        let shades = phosphor::shades(frame_buffer);
+        Screen::new(&shades, mode, &Theme::classic()).render(area, &mut buf);
+        buf
+    }
+
+    #[test]
+    fn sizes() {
+        let terminal = Rect::new(0, 0, 200, 60);
+
+        assert_eq!(
+            (64, 16),
+            RenderMode::HalfBlock.size(terminal, Some(1), HEIGHT)
+        );
+        assert_eq!(
+            (192, 48),
+            RenderMode::HalfBlock.size(terminal, None, HEIGHT)
+        );
+        assert_eq!((32, 8), RenderMode::Braille.size(terminal, Some(1), HEIGHT));
+        assert_eq!(
+            (64, 16),
+            RenderMode::Quadrant.size(terminal, Some(1), HEIGHT)
+        );
+        assert_eq!((128, 32), RenderMode::Ascii.size(terminal, Some(1), HEIGHT));
+        assert_eq!((200, 60), RenderMode::Block.size(terminal, Some(1), HEIGHT));
+        assert_eq!(
+            (64, 32),
+            RenderMode::HalfBlock.size(terminal, Some(1), 64),
+            "hi-res"
+        );
+        assert_eq!(
+            (192, 48),
+            RenderMode::HalfBlock.size(terminal, Some(8), HEIGHT),
+            "scale should shrink to fit"
+        );
+    }
+
+    #[test]
+    fn shrinks_to_fit() {
+        let small = Rect::new(0, 0, 40, 30);
+
+        let (width, height) = RenderMode::HalfBlock.size(small, Some(1), HEIGHT);
+
+        assert_eq!((40, 10), (width, height));
+    }
+
+    #[test]
+    fn modes() {
+        let mut frame_buffer = [[false; 64]; 64];
+        frame_buffer[0][0] = true;
+        frame_buffer[1][1] = true;
+
+        let buf = draw(&frame_buffer, RenderMode::HalfBlock, 64, 16);
+        assert_eq!("▀", buf.get(0, 0).symbol());
+        assert_eq!("▄", buf.get(1, 0).symbol());
+        assert_eq!(Color::Rgb(0x33, 0xff, 0x33), buf.get(0, 0).fg);
+
+        let buf = draw(&frame_buffer, RenderMode::Quadrant, 32, 16);
+        assert_eq!("▚", buf.get(0, 0).symbol());
+
+        let buf = draw(&frame_buffer, RenderMode::Braille, 32, 8);
+        assert_eq!("⠑", buf.get(0, 0).symbol());
+
+        let buf = draw(&frame_buffer, RenderMode::Ascii, 128, 32);
+        assert_eq!("#", buf.get(0, 0).symbol());
+        assert_eq!("#", buf.get(1, 0).symbol());
+        assert_eq!(" ", buf.get(2, 0).symbol());
+    }
+
+    #[test]
+    fn shaded() {
+        let mut shades = [[0; 64]; 64];
+        shades[0][0] = 255;
+        shades[1][0] = 128;
+        let theme = Theme::classic();
+        let area = Rect::new(0, 0, 64, 16);
+        let mut buf = Buffer::empty(area);
+
+        Screen::new(&shades, RenderMode::HalfBlock, &theme).render(area, &mut buf);
+
+        let cell = buf.get(0, 0);
+        assert_eq!("▀", cell.symbol());
+        assert_eq!(rgb(theme.foreground()), cell.fg);
+        assert_eq!(rgb(theme.shade(128)), cell.bg, "dimmer bottom half");
+    }
+
+    #[test]
+    fn hires() {
+        let mut shades = [[0; 64]; 64];
+        shades[63][0] = 255;
+        let area = Rect::new(0, 0, 64, 32);
+        let mut buf = Buffer::empty(area);
+
+        Screen::new(&shades, RenderMode::HalfBlock, &Theme::classic())
+            .rows(64)
+            .render(area, &mut buf);
+
+        assert_eq!("▄", buf.get(0, 31).symbol());
+    }
+
+    #[test]
+    fn chip8x_colours() {
+        let mut shades = [[0; 64]; 64];
+        shades[0][0] = 255;
+        shades[0][8] = 255;
+        let mut colours = Colours::default();
+        colours.zones[0][1] = chip8x::GREEN;
+        let area = Rect::new(0, 0, 64, 16);
+        let mut buf = Buffer::empty(area);
+
+        Screen::new(&shades, RenderMode::HalfBlock, &Theme::classic())
+            .colours(Some(&colours))
+            .render(area, &mut buf);
+
+        assert_eq!(Color::Rgb(0xff, 0x00, 0x00), buf.get(0, 0).fg);
+        assert_eq!(Color::Rgb(0x00, 0xff, 0x00), buf.get(8, 0).fg);
+        assert_eq!(Color::Rgb(0x00, 0x00, 0xff), buf.get(1, 0).bg);
+    }
+
+    #[test]
+    fn scales_up() {
+        let mut frame_buffer = [[false; 64]; 64];
+        frame_buffer[0][0] = true;
+
+        let buf = draw(&frame_buffer, RenderMode::HalfBlock, 128, 32);
+
+        assert_eq!("█", buf.get(0, 0).symbol());
+        assert_eq!("█", buf.get(1, 0).symbol());
+        assert_eq!(" ", buf.get(2, 0).symbol());
+        assert_eq!(" ", buf.get(0, 1).symbol());
+    }
+}
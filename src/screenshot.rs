@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use chip8::Theme;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// each CHIP-8 pixel becomes a SCALE x SCALE square in the image
+const SCALE: usize = 8;
+
+/// Saves the rows of `shades` on screen as a binary PPM in `dir`, named after the ROM
+/// with the first free index, e.g. `ibm-0.ppm`. Returns the path written.
+pub fn save(shades: &[[u8; 64]], theme: &Theme, dir: &Path, rom_name: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("could not create {}", dir.display()))?;
+    let path = (0..)
+        .map(|n| dir.join(format!("{}-{}.ppm", rom_name, n)))
+        .find(|path| !path.exists())
+        .unwrap();
+    fs::write(&path, ppm(shades, theme))
+        .with_context(|| format!("could not write {}", path.display()))?;
+    Ok(path)
+}
+
+fn ppm(shades: &[[u8; 64]], theme: &Theme) -> Vec<u8> {
+    let (width, height) = (64 * SCALE, shades.len() * SCALE);
+    let mut image = format!("P6 {} {} 255\n", width, height).into_bytes();
+    for row in shades {
+        for _ in 0..SCALE {
+            for shade in row {
+                let rgb = theme.shade(*shade);
+                for _ in 0..SCALE {
+                    image.extend_from_slice(&rgb);
+                }
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ppm_image() {
+        let mut shades = [[0; 64]; 64];
+        shades[0][0] = 255;
+
+        let theme = Theme {
+            palette: [[0, 0, 0], [1, 2, 3], [0, 0, 0], [0, 0, 0]],
+            ..Theme::default()
+        };
+
+        let image = ppm(&shades[..32], &theme);
+
+        let header = b"P6 512 256 255\n";
+        assert_eq!(header, &image[..header.len()]);
+        assert_eq!(header.len() + 512 * 256 * 3, image.len());
+        assert_eq!([1, 2, 3], image[header.len()..header.len() + 3]);
+        assert_eq!(
+            [0, 0, 0],
+            image[header.len() + SCALE * 3..header.len() + SCALE * 3 + 3],
+            "second pixel is background"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+/// A colour as red, green and blue.
+pub type Rgb = [u8; 3];
+
+/// Colours for the screen. The palette is indexed by which bit planes a pixel is lit
+/// in: 0 is the background, 1 the first plane, 2 the second and 3 both. Plain CHIP-8
+/// only ever uses the first two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub palette: [Rgb; 4],
+    /// the frame around the screen
+    pub border: Rgb,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::classic()
+    }
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 5] = ["classic", "amber", "white", "lcd", "contrast"];
+
+    /// Green phosphor.
+    pub fn classic() -> Theme {
+        Theme {
+            palette: [
+                [0x00, 0x00, 0x00],
+                [0x33, 0xff, 0x33],
+                [0x11, 0x88, 0x11],
+                [0xbb, 0xff, 0xbb],
+            ],
+            border: [0x11, 0x88, 0x11],
+        }
+    }
+
+    /// Amber phosphor.
+    pub fn amber() -> Theme {
+        Theme {
+            palette: [
+                [0x00, 0x00, 0x00],
+                [0xff, 0xb0, 0x00],
+                [0x99, 0x5c, 0x00],
+                [0xff, 0xe0, 0x99],
+            ],
+            border: [0x99, 0x5c, 0x00],
+        }
+    }
+
+    /// White on black.
+    pub fn white() -> Theme {
+        Theme {
+            palette: [
+                [0x00, 0x00, 0x00],
+                [0xff, 0xff, 0xff],
+                [0x80, 0x80, 0x80],
+                [0xc0, 0xc0, 0xc0],
+            ],
+            border: [0x80, 0x80, 0x80],
+        }
+    }
+
+    /// Dark pixels on a green handheld LCD.
+    pub fn lcd() -> Theme {
+        Theme {
+            palette: [
+                [0x9b, 0xbc, 0x0f],
+                [0x0f, 0x38, 0x0f],
+                [0x8b, 0xac, 0x0f],
+                [0x30, 0x62, 0x30],
+            ],
+            border: [0x30, 0x62, 0x30],
+        }
+    }
+
+    /// Black and saturated colours that stay distinct with colour blindness.
+    pub fn contrast() -> Theme {
+        Theme {
+            palette: [
+                [0x00, 0x00, 0x00],
+                [0xff, 0xff, 0x00],
+                [0x00, 0xff, 0xff],
+                [0xff, 0xff, 0xff],
+            ],
+            border: [0xff, 0xff, 0xff],
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "classic" | "green" => Some(Theme::classic()),
+            "amber" => Some(Theme::amber()),
+            "white" => Some(Theme::white()),
+            "lcd" => Some(Theme::lcd()),
+            "contrast" => Some(Theme::contrast()),
+            _ => None,
+        }
+    }
+
+    pub fn background(&self) -> Rgb {
+        self.palette[0]
+    }
+
+    pub fn foreground(&self) -> Rgb {
+        self.palette[1]
+    }
+
+    /// The background blended towards the foreground, for pixels fading out.
+    /// 0 is the background and 255 the foreground.
+    pub fn shade(&self, level: u8) -> Rgb {
+        let (bg, fg) = (self.background(), self.foreground());
+        let mix = |c: usize| {
+            let (bg, fg, level) = (bg[c] as u32, fg[c] as u32, level as u32);
+            ((bg * (255 - level) + fg * level) / 255) as u8
+        };
+        [mix(0), mix(1), mix(2)]
+    }
+
+    /// The colour of a pixel lit in the given planes.
+    pub fn color(&self, planes: u8) -> Rgb {
+        self.palette[planes as usize & 0b11]
+    }
+}
+
+/// Parses `#rrggbb`.
+pub fn parse_rgb(text: &str) -> Option<Rgb> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named() {
+        for name in Theme::NAMES {
+            assert!(Theme::named(name).is_some(), "{}", name);
+        }
+        assert_eq!(Some(Theme::classic()), Theme::named("green"));
+        assert_eq!(None, Theme::named("pink"));
+        assert_eq!(Theme::classic(), Theme::default());
+    }
+
+    #[test]
+    fn planes() {
+        let theme = Theme::lcd();
+
+        assert_eq!(theme.background(), theme.color(0));
+        assert_eq!(theme.foreground(), theme.color(1));
+        assert_eq!([0x30, 0x62, 0x30], theme.color(3));
+    }
+
+    #[test]
+    fn shades() {
+        let theme = Theme::amber();
+
+        assert_eq!(theme.background(), theme.shade(0));
+        assert_eq!(theme.foreground(), theme.shade(255));
+        assert_eq!([0x80, 0x58, 0x00], theme.shade(128));
+    }
+
+    #[test]
+    fn rgb() {
+        assert_eq!(Some([0xff, 0xb0, 0x00]), parse_rgb("#ffb000"));
+        assert_eq!(Some([0x12, 0x34, 0x56]), parse_rgb("#123456"));
+        assert_eq!(None, parse_rgb("ffb000"));
+        assert_eq!(None, parse_rgb("#ffb00"));
+        assert_eq!(None, parse_rgb("#gg0000"));
+    }
+}
@@ -0,0 +1,183 @@
+//! How many instructions fit in a 60Hz frame.
+//!
+//! The COSMAC VIP's interpreter took very different times for different instructions:
+//! clearing the screen took as long as sixty register loads. Counting
+//! the machine cycles each instruction took there, rather than running a flat number
+//! per frame, reproduces the speed old games and demos were tuned for. The costs are
+//! approximate, measured in machine cycles of 8 clocks of the 1.76MHz CDP1802.
+
+use crate::emulator::{Fault, System};
+use crate::op_code::OpCode;
+use std::fmt;
+
+/// Machine cycles between two display interrupts.
+pub const VIP_FRAME_CYCLES: i32 = 3668;
+
+// cycles the display takes each frame: DMA of 128 scanlines of 8 bytes, and the
+// interrupt routine that sets it up and ticks the timers
+const DISPLAY_CYCLES: i32 = 1024 + 46;
+
+// fetching and decoding, paid by every instruction
+const FETCH_CYCLES: u32 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    /// a fixed number of instructions per frame
+    Ipf(u32),
+    /// as many instructions as the COSMAC VIP fitted in a frame
+    Vip,
+}
+
+impl Timing {
+    /// Runs one 60Hz frame. Returns whether the screen changed.
+    pub fn run_frame(self, system: &mut System) -> Result<bool, Fault> {
+        match self {
+            Timing::Ipf(ipf) => system.run_frame(ipf),
+            Timing::Vip => system.run_vip_frame(),
+        }
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timing::Ipf(ipf) => write!(f, "{} ipf", ipf),
+            Timing::Vip => write!(f, "VIP timing"),
+        }
+    }
+}
+
+/// Cycles left for instructions in each frame once the display has had its share.
+pub fn vip_budget() -> i32 {
+    VIP_FRAME_CYCLES - DISPLAY_CYCLES
+}
+
+/// The machine cycles `op` took on the VIP, given the registers `v` from before running
+/// it and the state of `system` just after.
+pub fn vip_cycles(op: &OpCode, v: &[u8; 16], system: &System) -> u32 {
+    // skips cost a little more when taken
+    let skip = |taken: bool| if taken { 14 } else { 10 };
+    FETCH_CYCLES
+        + match *op {
+            // a loop clearing all 256 bytes of the display
+            OpCode::Cls => 24 + 3054,
+            OpCode::Ret => 10,
+            OpCode::Jmp(_) => 12,
+            OpCode::Call(_) => 26,
+            OpCode::Se { vx, value } => skip(v[vx] == value),
+            OpCode::Sne { vx, value } => skip(v[vx] != value),
+            OpCode::SeVxVy { vx, vy } => skip(v[vx] == v[vy]),
+            OpCode::SneVxVy { vx, vy } => skip(v[vx] != v[vy]),
+            OpCode::LdVx { .. } => 6,
+            OpCode::AddVx { .. } => 10,
+            // the 8XYN instructions build and run a little machine code routine
+            OpCode::LdVxVy { .. }
+            | OpCode::OrVxVy { .. }
+            | OpCode::AndVxVy { .. }
+            | OpCode::XorVxVy { .. }
+            | OpCode::AddVxVy { .. }
+            | OpCode::Sub { .. }
+            | OpCode::SubN { .. }
+            | OpCode::Shr { .. }
+            | OpCode::Shl { .. } => 44,
+            OpCode::LdI(_) => 12,
+            // a little longer when the addition carries into the high byte
+            OpCode::JmpV0(addr) => match (system.pc() ^ addr) & 0xFF00 {
+                0 => 22,
+                _ => 24,
+            },
+            OpCode::Rnd { .. } => 36,
+            // each row is shifted into place a bit at a time, then XORed into two bytes
+            OpCode::Drw { vx, n, .. } => {
+                let shift = (v[vx] % 8) as u32;
+                26 + n as u32 * (46 + 8 * shift)
+            }
+            OpCode::AddIVx(_) => 16,
+            OpCode::LdIVx(vx) | OpCode::LdVxI(vx) => 14 + 14 * (vx as u32 + 1),
+            OpCode::LdVxK(_) => 10,
+            OpCode::LdDtVx(_) | OpCode::LdStVx(_) | OpCode::LdVxDt(_) => 10,
+            // the keypad is scanned for the one key
+            OpCode::Skp(vx) => 4 + skip(system.key() == Some(v[vx])),
+            OpCode::Sknp(vx) => 4 + skip(system.key() != Some(v[vx])),
+            OpCode::LdFVx(_) => 16,
+            // each digit is found by repeated subtraction
+            OpCode::LdBVx(vx) => {
+                let digits = v[vx] / 100 + v[vx] / 10 % 10 + v[vx] % 10;
+                24 + 16 * digits as u32
+            }
+            // CHIP-8X, roughly as long as the instructions they resemble
+            OpCode::StepBg => 10,
+            OpCode::AddNibbles { .. } => 44,
+            OpCode::Colour { n, .. } => 26 + 20 * n.max(1) as u32,
+            OpCode::SkpKey2(_) | OpCode::SknpKey2(_) => 18,
+            OpCode::OutVx(_) | OpCode::InVx(_) => 10,
+            OpCode::Unknown => 0,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::Rom;
+
+    #[test]
+    fn costs() {
+        let system = System::new();
+        let mut v = [0; 16];
+        v[0] = 3;
+
+        assert_eq!(
+            46,
+            vip_cycles(&OpCode::LdVx { vx: 0, value: 1 }, &v, &system)
+        );
+        assert!(
+            vip_cycles(&OpCode::Drw { vx: 0, vy: 0, n: 5 }, &v, &system)
+                > vip_cycles(&OpCode::Drw { vx: 1, vy: 0, n: 5 }, &v, &system),
+            "unaligned sprites should take longer"
+        );
+        assert!(vip_cycles(&OpCode::Cls, &v, &system) as i32 > vip_budget());
+    }
+
+    #[test]
+    fn drw_vf_cost() {
+        // LD VF, 3 / LD I, 0 / DRW VF, V0, 5, unaligned until the flag clears VF
+        let rom = Rom::new(vec![0x6F, 0x03, 0xA0, 0x00, 0xDF, 0x05]);
+        let mut system = System::init(&rom).unwrap();
+        system.step().unwrap();
+        system.step().unwrap();
+        let v = *system.v();
+
+        let op = system.step().unwrap();
+
+        assert_eq!(0, system.v()[0xF]);
+        assert_eq!(
+            vip_cycles(
+                &OpCode::Drw {
+                    vx: 0xF,
+                    vy: 0,
+                    n: 5
+                },
+                &v,
+                &system
+            ),
+            vip_cycles(&op, &v, &system)
+        );
+        assert!(
+            vip_cycles(&op, &v, &system) > vip_cycles(&op, system.v(), &system),
+            "should cost the shift by 3 from before the draw"
+        );
+    }
+
+    #[test]
+    fn vip_speed() {
+        // ADD V0, 1 / JP 0x200
+        let rom = Rom::new(vec![0x70, 0x01, 0x12, 0x00]);
+        let mut system = System::init(&rom).unwrap();
+
+        Timing::Vip.run_frame(&mut system).unwrap();
+
+        // 2598 cycles fit 25 loops of 50 and 52 and an ADD that runs past the end
+        assert_eq!(51, system.instructions());
+        assert_eq!(26, system.v()[0]);
+    }
+}
@@ -0,0 +1,283 @@
+//! A COSMAC VIP: a CDP1802 with 4K of RAM, the CDP1861 display and the hex keypad,
+//! running the original CHIP-8 interpreter rather than `System`'s reimplementation.
+//! That makes it slow but exact, down to 0NNN calls into 1802 machine code.
+//!
+//! The interpreter and the monitor ROM its interrupt routine lives in are RCA's and not
+//! included; they have to be dumped from a VIP or found in its manual.
+
+use crate::cdp1802::{Bus, Cpu};
+use crate::emulator::{Fault, FrameBuffer};
+use crate::rom::Rom;
+use anyhow::{bail, Result};
+
+const RAM_SIZE: usize = 4096;
+const MONITOR_START: u16 = 0x8000;
+
+// the 1861 draws 262 lines of 14 machine cycles each frame, 128 of them from memory
+const LINES: u32 = 262;
+const LINE_CYCLES: i32 = 14;
+const FIRST_LINE: u32 = 80;
+const LAST_LINE: u32 = FIRST_LINE + 128;
+// the interrupt comes 29 cycles, about 2 lines, before the first line is drawn
+const INTERRUPT_LINE: u32 = FIRST_LINE - 2;
+
+// memory, the display and the keypad, as seen by the CPU
+struct Hardware {
+    ram: [u8; RAM_SIZE],
+    monitor: Vec<u8>,
+    // after a reset the monitor appears at 0 until it jumps to its real address
+    monitor_at_zero: bool,
+    display_on: bool,
+    // EF1, the 1861 saying it is about to start or finish drawing
+    display_flag: bool,
+    // the keypad key selected with OUT 2, which EF3 reports on
+    key_latch: u8,
+    key: Option<u8>,
+}
+
+impl Bus for Hardware {
+    fn read(&mut self, addr: u16) -> u8 {
+        if addr >= MONITOR_START {
+            self.monitor_at_zero = false;
+        }
+        match addr >= MONITOR_START || self.monitor_at_zero {
+            true => {
+                let offset = addr as usize % self.monitor.len().max(1);
+                self.monitor.get(offset).copied().unwrap_or(0xFF)
+            }
+            false => self.ram[addr as usize % RAM_SIZE],
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if addr < MONITOR_START {
+            self.ram[addr as usize % RAM_SIZE] = value;
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            1 => self.display_on = false,
+            2 => self.key_latch = value & 0x0F,
+            _ => {}
+        }
+    }
+
+    fn input(&mut self, port: u8) -> u8 {
+        if port == 1 {
+            self.display_on = true;
+        }
+        0xFF
+    }
+
+    fn flag(&self, n: u8) -> bool {
+        match n {
+            1 => self.display_flag,
+            3 => self.key == Some(self.key_latch),
+            _ => false,
+        }
+    }
+}
+
+pub struct Vip {
+    cpu: Cpu,
+    hardware: Hardware,
+    frame_buffer: FrameBuffer,
+    // 1802 instructions executed since power on
+    instructions: u64,
+    // cycles left in the line, negative when an instruction ran into the next
+    cycles: i32,
+}
+
+impl Vip {
+    /// A VIP with `interpreter` at 0 and `rom` after it, powered on. With a monitor the
+    /// machine starts in it as a real one would, and it runs the program in RAM as the
+    /// keypad's C key is not held down; without one it starts straight at 0.
+    pub fn init(monitor: Option<Vec<u8>>, interpreter: &[u8], rom: &Rom) -> Result<Vip> {
+        rom.validate()?;
+        if interpreter.len() > rom.load_address as usize {
+            bail!(
+                "the interpreter is {} bytes, which overlaps the ROM at {:#05X}",
+                interpreter.len(),
+                rom.load_address
+            );
+        }
+        let mut ram = [0; RAM_SIZE];
+        ram[..interpreter.len()].copy_from_slice(interpreter);
+        let start = rom.load_address as usize;
+        ram[start..start + rom.data.len()].copy_from_slice(&rom.data);
+        Ok(Vip {
+            cpu: Cpu::new(),
+            hardware: Hardware {
+                ram,
+                monitor_at_zero: monitor.is_some(),
+                monitor: monitor.unwrap_or_default(),
+                display_on: false,
+                display_flag: false,
+                key_latch: 0,
+                key: None,
+            },
+            frame_buffer: [[false; 64]; 64],
+            instructions: 0,
+            cycles: 0,
+        })
+    }
+
+    /// Runs one 60Hz frame of the 1861 and returns whether the screen changed. Each
+    /// row of the CHIP-8 screen is 4 lines of the 1861's 128; the first of them is shown.
+    pub fn run_frame(&mut self) -> Result<bool, Fault> {
+        let before = self.frame_buffer;
+        if !self.hardware.display_on {
+            self.frame_buffer = [[false; 64]; 64];
+        }
+        for line in 0..LINES {
+            let display_on = self.hardware.display_on;
+            self.hardware.display_flag = display_on
+                && ((FIRST_LINE - 4..FIRST_LINE).contains(&line)
+                    || (LAST_LINE - 4..LAST_LINE).contains(&line));
+            let mut cycles = LINE_CYCLES;
+            if display_on && line == INTERRUPT_LINE && self.cpu.interrupt() {
+                cycles -= 1;
+            }
+            if display_on && (FIRST_LINE..LAST_LINE).contains(&line) {
+                // 8 DMA cycles at the start of the line take a byte of pixels each
+                let row = (line - FIRST_LINE) as usize;
+                for byte in 0..8 {
+                    let value = self.cpu.dma_out(&mut self.hardware);
+                    if row.is_multiple_of(4) {
+                        for bit in 0..8 {
+                            self.frame_buffer[row / 4][byte * 8 + bit] = value & (0x80 >> bit) != 0;
+                        }
+                    }
+                }
+                cycles -= 8;
+            }
+
+            self.cycles += cycles;
+            while self.cycles > 0 {
+                if self.cpu.idle {
+                    // sleeps until the next interrupt or DMA
+                    self.cycles = 0;
+                    break;
+                }
+                self.cycles -= self.cpu.step(&mut self.hardware)? as i32;
+                self.instructions += 1;
+            }
+        }
+        Ok(self.frame_buffer != before)
+    }
+
+    pub fn frame_buffer(&self) -> &FrameBuffer {
+        &self.frame_buffer
+    }
+
+    pub fn set_key(&mut self, key: Option<u8>) {
+        self.hardware.key = key;
+    }
+
+    pub fn key(&self) -> Option<u8> {
+        self.hardware.key
+    }
+
+    /// Whether Q is driving the speaker.
+    pub fn sound_active(&self) -> bool {
+        self.cpu.q
+    }
+
+    /// How many 1802 instructions have been executed.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// All 4K of RAM.
+    pub fn memory(&self) -> &[u8] {
+        &self.hardware.ram
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sets up an interrupt routine that shows the page at 0xF00, moves the program
+    // counter off R0 which DMA uses, turns on the display and waits
+    const PROGRAM: [u8; 23] = [
+        0xF8, 0x01, 0xB1, // LDI 01 / PHI 1
+        0xF8, 0x08, 0xA1, // LDI 08 / PLO 1
+        0xF8, 0x0E, 0xB2, // LDI 0E / PHI 2
+        0xF8, 0xFF, 0xA2, // LDI FF / PLO 2
+        0xF8, 0x00, 0xB3, // LDI 00 / PHI 3
+        0xF8, 0x13, 0xA3, // LDI 13 / PLO 3
+        0xD3, 0xE2, 0x69, // SEP 3 / SEX 2 / INP 1
+        0x30, 0x15, // BR 15
+    ];
+    // at 0x106: exit with LDXA / RET, entry at 0x108 saving T and D then pointing R0
+    // at 0xF00
+    const INTERRUPT: [u8; 14] = [
+        0x72, 0x70, 0x22, 0x78, 0x22, 0x52, 0xF8, 0x0F, 0xB0, 0xF8, 0x00, 0xA0, 0x30, 0x06,
+    ];
+
+    fn vip() -> Vip {
+        let mut interpreter = PROGRAM.to_vec();
+        interpreter.resize(0x106, 0);
+        interpreter.extend(INTERRUPT);
+        let mut vip = Vip::init(None, &interpreter, &Rom::new(vec![0x12, 0x00])).unwrap();
+        vip.hardware.ram[0xF00] = 0xF0;
+        vip.hardware.ram[0xF00 + 8 * 4 + 7] = 0x01;
+        vip
+    }
+
+    #[test]
+    fn display() {
+        let mut vip = vip();
+
+        assert!(
+            vip.run_frame().unwrap(),
+            "display should be on by the first interrupt"
+        );
+        assert!(!vip.run_frame().unwrap());
+
+        let frame_buffer = vip.frame_buffer();
+        assert!(frame_buffer[0][..4].iter().all(|px| *px));
+        assert!(!frame_buffer[0][4]);
+        assert!(frame_buffer[1][63], "rows should be 4 lines apart");
+        // the rest of the screen is 0xF00 onwards, then the program as memory wraps
+        assert_eq!(
+            5,
+            frame_buffer[..8].iter().flatten().filter(|px| **px).count()
+        );
+        assert!(vip.cpu().ie, "interrupt routine should have returned");
+        assert_eq!(3, vip.cpu().p);
+    }
+
+    #[test]
+    fn keypad() {
+        let mut vip = vip();
+        vip.hardware.key_latch = 0x5;
+
+        vip.set_key(Some(0x5));
+        assert!(vip.hardware.flag(3));
+        vip.set_key(Some(0x6));
+        assert!(!vip.hardware.flag(3));
+    }
+
+    #[test]
+    fn monitor_at_reset() {
+        let mut monitor = vec![0; 0x200];
+        // LBR 8003 / LDI 42
+        monitor[..5].copy_from_slice(&[0xC0, 0x80, 0x03, 0xF8, 0x42]);
+        let mut vip = Vip::init(Some(monitor), &[], &Rom::new(vec![0x12, 0x00])).unwrap();
+
+        for _ in 0..2 {
+            vip.cpu.step(&mut vip.hardware).unwrap();
+        }
+
+        assert_eq!(0x42, vip.cpu().d);
+        assert_eq!(0x8005, vip.cpu().r[0]);
+        assert!(!vip.hardware.monitor_at_zero, "RAM should be back at 0");
+    }
+}
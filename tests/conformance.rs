@@ -0,0 +1,207 @@
+//! Runs test ROMs without a display and compares the screen each ends on with a golden
+//! image in `tests/golden`, one line per row with `#` for a lit pixel.
+//!
+//! The test ROMs are checked in as built in `tests/roms`, next to their sources, so a
+//! bug in the assembler can't change what they test. Each draws a tick per check that
+//! passes, or a digit for each quirk it finds, and its golden images are its pass
+//! screens: all ticks, and the quirks each platform is documented to have. IBM is in
+//! `roms/`. `CHIP8_UPDATE_GOLDEN=1 cargo test` rewrites the golden images, which then
+//! need checking by hand.
+
+use chip8::frontend::{self, Headless, Input, RunOptions};
+use chip8::timing::Timing;
+use chip8::{asm, Quirks, Rom, System, ROM_START};
+use std::{env, fs, path::PathBuf};
+
+// how many differing pixels to list before giving up
+const MAX_DIFFS: usize = 10;
+
+// the test ROMs in `tests/roms`
+const TEST_ROMS: [&str; 4] = ["opcodes", "flags", "keypad", "quirks"];
+
+struct Case {
+    golden: &'static str,
+    rom: Rom,
+    quirks: Quirks,
+    timing: Timing,
+    frames: u64,
+    keys: Headless,
+}
+
+impl Case {
+    fn new(golden: &'static str, rom: Rom) -> Case {
+        Case {
+            golden,
+            rom,
+            quirks: Quirks::default(),
+            timing: Timing::Ipf(15),
+            frames: 60,
+            keys: Headless::new(),
+        }
+    }
+
+    /// A case for `tests/roms/<name>.ch8`.
+    fn test_rom(golden: &'static str, name: &str) -> Case {
+        let path = PathBuf::from("tests/roms").join(name).with_extension("ch8");
+        Case::new(golden, Rom::read(&path).unwrap())
+    }
+
+    fn quirks(mut self, quirks: Quirks) -> Case {
+        self.quirks = quirks;
+        self
+    }
+
+    fn frames(mut self, frames: u64) -> Case {
+        self.frames = frames;
+        self
+    }
+
+    /// Holds `key` down from `frame` on, or lets go with `None`.
+    fn key(mut self, frame: u64, key: Option<u8>) -> Case {
+        self.keys = self.keys.at(frame, Input::Key(key));
+        self
+    }
+
+    fn check(self) {
+        let mut system = System::init(&self.rom).unwrap();
+        system.set_quirks(self.quirks);
+        system.seed(0);
+        let (mut input, mut video, mut audio) = (self.keys, Headless::new(), Headless::new());
+        let options = RunOptions {
+            timing: self.timing,
+            realtime: false,
+            max_frames: Some(self.frames),
+            turbo: 0,
+        };
+        frontend::run(&mut system, &mut video, &mut input, &mut audio, options)
+            .unwrap_or_else(|err| panic!("{}: {:#}", self.golden, err));
+
+        let screen = screen(&system);
+        let golden = golden(self.golden, &screen);
+        let diffs = diff(&golden, &screen);
+        assert!(
+            diffs.is_empty(),
+            "{} with {:?}: {} pixels differ from the golden image, at {}\n\n{}",
+            self.golden,
+            self.quirks.name().unwrap_or("custom quirks"),
+            diffs.len(),
+            diffs
+                .iter()
+                .take(MAX_DIFFS)
+                .map(|(x, y)| format!("({}, {})", x, y))
+                .collect::<Vec<_>>()
+                .join(" "),
+            screen
+        );
+    }
+}
+
+// the golden image for `name`, or `screen` having written it as the new one
+fn golden(name: &str, screen: &str) -> String {
+    let path = PathBuf::from("tests/golden")
+        .join(name)
+        .with_extension("txt");
+    if env::var_os("CHIP8_UPDATE_GOLDEN").is_some() {
+        fs::write(&path, screen).unwrap();
+        return screen.to_string();
+    }
+    fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "{}: {}, run with CHIP8_UPDATE_GOLDEN=1",
+            path.display(),
+            err
+        )
+    })
+}
+
+fn screen(system: &System) -> String {
+    let mut screen = String::new();
+    for row in &system.frame_buffer()[..system.height()] {
+        screen.extend(row.iter().map(|px| if *px { '#' } else { '.' }));
+        screen.push('\n');
+    }
+    screen
+}
+
+// the pixels that differ, as (x, y), including any rows only one of them has
+fn diff(golden: &str, screen: &str) -> Vec<(usize, usize)> {
+    let (golden, screen): (Vec<_>, Vec<_>) = (golden.lines().collect(), screen.lines().collect());
+    let mut diffs = Vec::new();
+    for y in 0..golden.len().max(screen.len()) {
+        let expected = golden.get(y).copied().unwrap_or_default().as_bytes();
+        let actual = screen.get(y).copied().unwrap_or_default().as_bytes();
+        for x in 0..expected.len().max(actual.len()) {
+            if expected.get(x) != actual.get(x) {
+                diffs.push((x, y));
+            }
+        }
+    }
+    diffs
+}
+
+#[test]
+fn ibm() {
+    Case::new("ibm", Rom::read("roms/ibm.ch8".as_ref()).unwrap())
+        .frames(30)
+        .check();
+}
+
+#[test]
+fn opcodes() {
+    for quirks in [Quirks::default(), Quirks::vip(), Quirks::schip()] {
+        Case::test_rom("opcodes", "opcodes").quirks(quirks).check();
+    }
+}
+
+#[test]
+fn flags() {
+    for quirks in [Quirks::default(), Quirks::vip(), Quirks::schip()] {
+        Case::test_rom("flags", "flags").quirks(quirks).check();
+    }
+}
+
+#[test]
+fn keypad() {
+    Case::test_rom("keypad", "keypad")
+        .key(5, Some(0xA))
+        .key(10, None)
+        .key(15, Some(0x5))
+        .key(20, None)
+        .frames(30)
+        .check();
+}
+
+// each shows vf_reset, memory, shifting, clipping, display_wait and jumping as digits
+#[test]
+fn quirks() {
+    Case::test_rom("quirks-modern", "quirks").check();
+    Case::test_rom("quirks-vip", "quirks")
+        .quirks(Quirks::vip())
+        .check();
+    Case::test_rom("quirks-schip", "quirks")
+        .quirks(Quirks::schip())
+        .check();
+    Case::test_rom("quirks-xochip", "quirks")
+        .quirks(Quirks::xochip())
+        .check();
+}
+
+#[test]
+fn sources_match_roms() {
+    for name in TEST_ROMS {
+        let path = PathBuf::from("tests/roms").join(name);
+        let source = fs::read_to_string(path.with_extension("asm")).unwrap();
+        let built = asm::assemble(&source, ROM_START).unwrap();
+        assert_eq!(
+            fs::read(path.with_extension("ch8")).unwrap(),
+            built,
+            "{}.ch8 is out of date, rebuild it with `chip8 asm`",
+            name
+        );
+    }
+}
+
+#[test]
+fn diffs() {
+    assert_eq!(vec![(1, 0), (0, 2)], diff("#.\n..\n", "##\n..\n#\n"));
+}
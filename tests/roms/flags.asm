@@ -0,0 +1,103 @@
+; Checks that the arithmetic instructions set VF after writing their result, so that
+; with VF as the destination the flag is what's left. Draws a tick for a pass or a
+; cross for a failure, in the order 8XY4 8XY5 8XY6 8XY7 8XYE, then the same with a
+; carry or borrow the other way, then 8XY5 and 8XY7 with equal operands, which don't
+; borrow.
+
+start:  LD VC, 0                ; cursor
+        LD VD, 0
+
+        ; 8FY4
+        LD VF, 0xF0
+        LD V1, 0x20
+        ADD VF, V1
+        CALL flag1
+
+        ; 8FY5
+        LD VF, 0x30
+        LD V1, 0x10
+        SUB VF, V1
+        CALL flag1
+
+        ; 8FY6, shifting VF into itself
+        LD VF, 0x03
+        SHR VF
+        CALL flag1
+
+        ; 8FY7
+        LD VF, 0x10
+        LD V1, 0x30
+        SUBN VF, V1
+        CALL flag1
+
+        ; 8FYE
+        LD VF, 0x81
+        SHL VF
+        CALL flag1
+
+        ; 8FY4 without a carry, which would leave 0x30
+        LD VF, 0x10
+        LD V1, 0x20
+        ADD VF, V1
+        CALL flag0
+
+        ; 8FY5 with a borrow, which would leave 0xF0
+        LD VF, 0x10
+        LD V1, 0x20
+        SUB VF, V1
+        CALL flag0
+
+        ; 8FY6 shifting out a 0, which would leave 0x01
+        LD VF, 0x02
+        SHR VF
+        CALL flag0
+
+        ; 8FY7 with a borrow, which would leave 0xF0
+        LD VF, 0x20
+        LD V1, 0x10
+        SUBN VF, V1
+        CALL flag0
+
+        ; 8FYE shifting out a 0, which would leave 0x02
+        LD VF, 0x01
+        SHL VF
+        CALL flag0
+
+        ; 8FY5 with equal operands
+        LD VF, 0x10
+        LD V1, 0x10
+        SUB VF, V1
+        CALL flag1
+
+        ; 8FY7 with equal operands
+        LD VF, 0x10
+        LD V1, 0x10
+        SUBN VF, V1
+        CALL flag1
+
+halt:   JP halt
+
+; passes when VF is 1
+flag1:  LD VE, 0
+        SNE VF, 1
+        LD VE, 1
+        JP result
+
+; passes when VF is 0
+flag0:  LD VE, 0
+        SNE VF, 0
+        LD VE, 1
+; draws a tick when VE is 1 and a cross otherwise, then moves the cursor on
+result: LD I, tick
+        SE VE, 1
+        LD I, cross
+        DRW VC, VD, 5
+        ADD VC, 8
+        SE VC, 40
+        RET
+        LD VC, 0
+        ADD VD, 6
+        RET
+
+tick:   DB 0x02, 0x04, 0x88, 0x50, 0x20
+cross:  DB 0x88, 0x50, 0x20, 0x50, 0x88
@@ -0,0 +1,28 @@
+; Waits for a key with FX0A and draws it, then draws a 5 once 5 is held down (EXA1)
+; and a 0 once it has been let go again (EX9E).
+
+start:  LD V1, 0                ; cursor
+        LD V2, 0
+        LD V0, K
+        CALL digit
+
+        LD V3, 5
+down:   SKNP V3
+        JP pressed
+        JP down
+pressed: LD V0, 5
+        CALL digit
+
+up:     SKP V3
+        JP released
+        JP up
+released: LD V0, 0
+        CALL digit
+
+halt:   JP halt
+
+; draws the digit in V0 and moves the cursor on
+digit:  LD F, V0
+        DRW V1, V2, 5
+        ADD V1, 5
+        RET
@@ -0,0 +1,280 @@
+; Checks each instruction and draws a tick for a pass or a cross for a failure, eight
+; to a row in the order below. Only uses behaviour every quirk profile agrees on.
+;
+;   row 1: 3XNN 4XNN 5XY0 9XY0 6XNN/7XNN 8XY0 8XY1 8XY2
+;   row 2: 8XY3 8XY4 8XY4 8XY5 8XY5 8XY7 8XY6 8XYE
+;   row 3: FX1E/FX55/FX65 FX33 FX29 FX15/FX07 2NNN/00EE BNNN CXNN DXYN
+
+start:  LD I, cross             ; something for CLS to clear
+        DRW V0, V0, 5
+        CLS
+        LD VC, 0                ; cursor
+        LD VD, 0
+
+        ; 3XNN
+        LD VE, 0
+        LD V0, 5
+        SE V0, 6
+        ADD VE, 1
+        SE V0, 5
+        LD VE, 0
+        CALL result
+
+        ; 4XNN
+        LD VE, 0
+        SNE V0, 5
+        ADD VE, 1
+        SNE V0, 6
+        LD VE, 0
+        CALL result
+
+        ; 5XY0
+        LD VE, 0
+        LD V1, 5
+        LD V2, 6
+        SE V0, V2
+        ADD VE, 1
+        SE V0, V1
+        LD VE, 0
+        CALL result
+
+        ; 9XY0
+        LD VE, 0
+        SNE V0, V1
+        ADD VE, 1
+        SNE V0, V2
+        LD VE, 0
+        CALL result
+
+        ; 6XNN and 7XNN, which leaves VF alone
+        LD VE, 0
+        LD VF, 7
+        LD V0, 0xF0
+        ADD V0, 0x20
+        SNE V0, 0x10
+        ADD VE, 1
+        SNE VF, 7
+        ADD VE, 1
+        CALL result2
+
+        ; 8XY0
+        LD VE, 0
+        LD V1, 0x33
+        LD V0, V1
+        SNE V0, 0x33
+        LD VE, 1
+        CALL result
+
+        ; 8XY1
+        LD VE, 0
+        LD V0, 0x0F
+        LD V1, 0x30
+        OR V0, V1
+        SNE V0, 0x3F
+        LD VE, 1
+        CALL result
+
+        ; 8XY2
+        LD VE, 0
+        LD V0, 0x3C
+        LD V1, 0x0F
+        AND V0, V1
+        SNE V0, 0x0C
+        LD VE, 1
+        CALL result
+
+        ; 8XY3
+        LD VE, 0
+        LD V0, 0x3C
+        XOR V0, V1
+        SNE V0, 0x33
+        LD VE, 1
+        CALL result
+
+        ; 8XY4 with a carry
+        LD VE, 0
+        LD V0, 0xF0
+        LD V1, 0x20
+        ADD V0, V1
+        SNE V0, 0x10
+        ADD VE, 1
+        SNE VF, 1
+        ADD VE, 1
+        CALL result2
+
+        ; 8XY4 without
+        LD VE, 0
+        ADD V0, V1
+        SNE V0, 0x30
+        ADD VE, 1
+        SNE VF, 0
+        ADD VE, 1
+        CALL result2
+
+        ; 8XY5 without a borrow
+        LD VE, 0
+        LD V1, 0x10
+        SUB V0, V1
+        SNE V0, 0x20
+        ADD VE, 1
+        SNE VF, 1
+        ADD VE, 1
+        CALL result2
+
+        ; 8XY5 with
+        LD VE, 0
+        LD V1, 0x30
+        SUB V0, V1
+        SNE V0, 0xF0
+        ADD VE, 1
+        SNE VF, 0
+        ADD VE, 1
+        CALL result2
+
+        ; 8XY7
+        LD VE, 0
+        LD V0, 0x10
+        SUBN V0, V1
+        SNE V0, 0x20
+        ADD VE, 1
+        SNE VF, 1
+        ADD VE, 1
+        CALL result2
+
+        ; 8XY6, shifting VX into itself so the shift quirk doesn't matter
+        LD VE, 0
+        LD V0, 0x05
+        SHR V0
+        SNE V0, 0x02
+        ADD VE, 1
+        SNE VF, 1
+        ADD VE, 1
+        CALL result2
+
+        ; 8XYE
+        LD VE, 0
+        LD V0, 0x81
+        SHL V0
+        SNE V0, 0x02
+        ADD VE, 1
+        SNE VF, 1
+        ADD VE, 1
+        CALL result2
+
+        ; FX1E, FX55 and FX65
+        LD VE, 0
+        LD I, scratch
+        LD V0, 2
+        ADD I, V0
+        LD V0, 0x5A
+        LD V1, 0xA5
+        LD [I], V1
+        LD V0, 0
+        LD V1, 0
+        LD I, scratch2
+        LD V1, [I]
+        SNE V0, 0x5A
+        ADD VE, 1
+        SNE V1, 0xA5
+        ADD VE, 1
+        CALL result2
+
+        ; FX33
+        LD VE, 0
+        LD V0, 254
+        LD I, scratch
+        LD B, V0
+        LD I, scratch
+        LD V2, [I]
+        SNE V0, 2
+        ADD VE, 1
+        SNE V1, 5
+        ADD VE, 1
+        SNE V2, 4
+        ADD VE, 1
+        ADD VE, 0xFE
+        CALL result
+
+        ; FX29, the second row of 7
+        LD VE, 0
+        LD V0, 7
+        LD F, V0
+        LD V1, [I]
+        SNE V1, 0x10
+        LD VE, 1
+        CALL result
+
+        ; FX15 and FX07
+        LD VE, 0
+        LD V0, 0x20
+        LD DT, V0
+        LD V1, DT
+        SE V1, 0
+        LD VE, 1
+        CALL result
+
+        ; 2NNN and 00EE
+        LD VE, 0
+        LD V0, 0
+        CALL set_v0
+        SNE V0, 0x77
+        LD VE, 1
+        CALL result
+
+        ; BNNN, landing on the second entry. jumps is at 0x3XX, so V3 is set too for the
+        ; jumping quirk, which adds VX instead of V0
+        LD V0, 4
+        LD V3, 4
+        JP V0, jumps
+jumped: CALL result
+
+        ; CXNN
+        LD VE, 0
+        RND V0, 0x0F
+        LD V1, 0xF0
+        AND V1, V0
+        SNE V1, 0
+        LD VE, 1
+        CALL result
+
+        ; DXYN sets VF on a collision only
+        LD VE, 0
+        LD I, tick
+        LD V0, 56
+        LD V1, 26
+        DRW V0, V1, 5
+        SNE VF, 0
+        ADD VE, 1
+        DRW V0, V1, 5
+        SNE VF, 1
+        ADD VE, 1
+        CALL result2
+
+halt:   JP halt
+
+jumps:  LD VE, 0
+        JP jumped
+        LD VE, 1
+        JP jumped
+
+set_v0: LD V0, 0x77
+        RET
+
+; counts two passes in VE as one
+result2: ADD VE, 0xFF
+; draws a tick when VE is 1 and a cross otherwise, then moves the cursor on
+result: LD I, tick
+        SE VE, 1
+        LD I, cross
+        DRW VC, VD, 5
+        ADD VC, 8
+        SE VC, 64
+        RET
+        LD VC, 0
+        ADD VD, 6
+        RET
+
+tick:   DB 0x02, 0x04, 0x88, 0x50, 0x20
+cross:  DB 0x88, 0x50, 0x20, 0x50, 0x88
+scratch: DB 0, 0
+scratch2: DB 0, 0, 0
@@ -0,0 +1,89 @@
+; Draws a 1 for each quirk that is on and a 0 for each that is off, in the order
+; vf_reset, memory, shifting, clipping, display_wait and jumping.
+
+start:  LD V8, 0                ; cursor
+        LD V9, 0
+
+        ; vf_reset: OR clears VF
+        LD VF, 5
+        LD V0, 1
+        OR V0, V0
+        LD VE, 0
+        SNE VF, 0
+        LD VE, 1
+        CALL digit
+
+        ; memory: FX65 leaves I past what it read
+        LD I, scratch
+        LD V0, [I]
+        LD V0, [I]
+        LD VE, 0
+        SNE V0, 0x22
+        LD VE, 1
+        CALL digit
+
+        ; shifting: 8XY6 shifts VX instead of VY
+        LD V0, 0x02
+        LD V1, 0x08
+        SHR V0, V1
+        LD VE, 0
+        SNE V0, 0x01
+        LD VE, 1
+        CALL digit
+
+        ; clipping: a sprite off the right edge doesn't come back on the left
+        LD I, line
+        LD V0, 60
+        LD V1, 28
+        DRW V0, V1, 1
+        LD I, dot
+        LD V0, 0
+        DRW V0, V1, 1
+        LD VE, 0
+        SNE VF, 0
+        LD VE, 1
+        DRW V0, V1, 1           ; then rubs both out again
+        LD I, line
+        LD V0, 60
+        DRW V0, V1, 1
+        CALL digit
+
+        ; display_wait: each DXYN waits for the next frame, so the delay timer runs
+        ; down while drawing
+        LD V0, 10
+        LD DT, V0
+        LD V0, 0
+        DRW V0, V0, 0
+        DRW V0, V0, 0
+        DRW V0, V0, 0
+        DRW V0, V0, 0
+        DRW V0, V0, 0
+        LD V0, DT
+        LD V1, 8
+        SUB V1, V0
+        LD VE, VF
+        CALL digit
+
+        ; jumping: BXNN adds VX rather than V0, X being the high nibble of the address,
+        ; which is V2 as everything here is below 0x300
+        LD V0, 0
+        LD V2, 4
+        JP V0, jumps
+jumped: CALL digit
+
+halt:   JP halt
+
+jumps:  LD VE, 0
+        JP jumped
+        LD VE, 1
+        JP jumped
+
+; draws VE as a digit and moves the cursor on
+digit:  LD F, VE
+        DRW V8, V9, 5
+        ADD V8, 5
+        RET
+
+scratch: DB 0x11, 0x22
+line:   DB 0xFF
+dot:    DB 0x80